@@ -0,0 +1,195 @@
+use definitions;
+use graphics::{AssetError, Graphics};
+use sdl2::rect::Rect;
+use sprite::{AnimatedSprite, AnimationMode, Frame, Sprite, UpdateAndDrawable};
+use units::Milliseconds;
+
+/// The frames of a single animation and how they are played.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Animation {
+    pub mode: AnimationMode,
+    pub frames: Vec<Frame>,
+}
+
+impl Animation {
+    /// Creates a sprite for the animation, animations with a single frame are
+    /// drawn as a still sprite.
+    pub fn load_sprite<'a>(
+        &self,
+        graphics: &mut Graphics<'a>,
+        sheet: &str,
+    ) -> Result<Box<UpdateAndDrawable<'a> + 'a>, AssetError> {
+        if self.frames.len() == 1 {
+            let source = self.frames[0].source;
+            Ok(Box::new(Sprite::new(
+                graphics,
+                sheet,
+                source.x(),
+                source.y(),
+                source.width(),
+                source.height(),
+            )?))
+        } else {
+            Ok(Box::new(AnimatedSprite::new(
+                graphics,
+                sheet,
+                self.frames.clone(),
+                self.mode,
+            )?))
+        }
+    }
+}
+
+/// A sprite sheet together with the animations cut from it, keyed by whatever
+/// the animation names are parsed into.
+pub struct AnimationSet<K> {
+    pub sheet: String,
+    pub animations: Vec<(K, Animation)>,
+}
+
+impl<K> AnimationSet<K> {
+    pub fn load<F>(path: &str, parse_name: F) -> Result<AnimationSet<K>, AssetError>
+    where
+        F: Fn(&str) -> Option<K>,
+    {
+        let text = definitions::read(path)?;
+        AnimationSet::parse(path, &text, parse_name)
+    }
+
+    /// Parses an animation file. It names the sprite sheet with
+    /// `sheet = content/MyChar.bmp` and has one animation per line:
+    /// `name = mode, x y width height duration, ...` with the mode being `loop`,
+    /// `ping_pong` or `once` and each frame's duration in milliseconds. Empty
+    /// lines and lines starting with `#` are ignored.
+    pub fn parse<F>(path: &str, text: &str, parse_name: F) -> Result<AnimationSet<K>, AssetError>
+    where
+        F: Fn(&str) -> Option<K>,
+    {
+        let mut sheet = None;
+        let mut animations = Vec::new();
+        for entry in definitions::entries(path, text) {
+            let (number, name, value) = entry?;
+            let error = |message: String| definitions::error(path, number, message);
+            if name == "sheet" {
+                sheet = Some(value.to_string());
+                continue;
+            }
+
+            let key =
+                parse_name(name).ok_or_else(|| error(format!("unknown animation {}", name)))?;
+            let mut fields = value.split(',').map(|field| field.trim());
+            let mode = match fields.next() {
+                Some("loop") => AnimationMode::Loop,
+                Some("ping_pong") => AnimationMode::PingPong,
+                Some("once") => AnimationMode::Once,
+                other => {
+                    return Err(error(format!("unknown mode {}", other.unwrap_or(""))));
+                }
+            };
+            let frames = fields
+                .map(|field| {
+                    parse_frame(field).ok_or_else(|| error(format!("bad frame `{}`", field)))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if frames.is_empty() {
+                return Err(error(format!("animation {} has no frames", name)));
+            }
+            animations.push((
+                key,
+                Animation {
+                    mode: mode,
+                    frames: frames,
+                },
+            ));
+        }
+
+        match sheet {
+            Some(sheet) => Ok(AnimationSet {
+                sheet: sheet,
+                animations: animations,
+            }),
+            None => Err(AssetError::Invalid(
+                path.to_string(),
+                "no sprite sheet given".to_string(),
+            )),
+        }
+    }
+}
+
+/// Parses `x y width height duration`.
+fn parse_frame(text: &str) -> Option<Frame> {
+    let numbers = text
+        .split_whitespace()
+        .map(|number| number.parse::<u32>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    match numbers[..] {
+        [x, y, width, height, duration] if width > 0 && height > 0 => Some(Frame::new(
+            Rect::new(x as i32, y as i32, width, height),
+            Milliseconds::new(duration),
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_name(name: &str) -> Option<u32> {
+        name.parse().ok()
+    }
+
+    #[test]
+    fn parses_animations() {
+        let text = "# comment\n\
+                    sheet = content/Test.bmp\n\
+                    \n\
+                    1 = loop, 0 32 32 32 100, 32 32 32 32 50\n\
+                    2 = once, 64 0 16 16 0\n\
+                    3 = ping_pong, 0 0 8 8 10, 8 0 8 8 10\n";
+        let set = AnimationSet::parse("Test.anim", text, parse_name).unwrap();
+        assert_eq!(set.sheet, "content/Test.bmp");
+        assert_eq!(set.animations.len(), 3);
+
+        let (key, ref animation) = set.animations[0];
+        assert_eq!(key, 1);
+        assert_eq!(animation.mode, AnimationMode::Loop);
+        assert_eq!(
+            animation.frames,
+            vec![
+                Frame::new(Rect::new(0, 32, 32, 32), Milliseconds::new(100)),
+                Frame::new(Rect::new(32, 32, 32, 32), Milliseconds::new(50)),
+            ]
+        );
+        assert_eq!(set.animations[1].1.mode, AnimationMode::Once);
+        assert_eq!(set.animations[2].1.mode, AnimationMode::PingPong);
+    }
+
+    #[test]
+    fn reports_line_of_errors() {
+        let text = "sheet = content/Test.bmp\n1 = loop, 0 0 32 32\n";
+        match AnimationSet::parse("Test.anim", text, parse_name) {
+            Err(AssetError::Invalid(location, message)) => {
+                assert_eq!(location, "Test.anim:2");
+                assert_eq!(message, "bad frame `0 0 32 32`");
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        match AnimationSet::parse(
+            "Test.anim",
+            "sheet = a.bmp\nwalk = loop, 0 0 1 1 1",
+            parse_name,
+        ) {
+            Err(AssetError::Invalid(_, message)) => assert_eq!(message, "unknown animation walk"),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn requires_a_sheet() {
+        match AnimationSet::parse("Test.anim", "1 = loop, 0 0 32 32 100", parse_name) {
+            Err(AssetError::Invalid(location, _)) => assert_eq!(location, "Test.anim"),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use definitions;
+use graphics::AssetError;
+use input::Control;
+use sdl2::controller::Button;
+use sdl2::keyboard::Keycode;
+use std::collections::BTreeMap;
+
+/// Everything the player can do, independent of which keys do it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    LookUp,
+    LookDown,
+    Jump,
+    Fire,
+    Pause,
+    Fullscreen,
+    Debug,
+    Screenshot,
+    Quit,
+}
+
+impl Action {
+    fn from_name(name: &str) -> Option<Action> {
+        match name {
+            "move_left" => Some(Action::MoveLeft),
+            "move_right" => Some(Action::MoveRight),
+            "look_up" => Some(Action::LookUp),
+            "look_down" => Some(Action::LookDown),
+            "jump" => Some(Action::Jump),
+            "fire" => Some(Action::Fire),
+            "pause" => Some(Action::Pause),
+            "fullscreen" => Some(Action::Fullscreen),
+            "debug" => Some(Action::Debug),
+            "screenshot" => Some(Action::Screenshot),
+            "quit" => Some(Action::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// Which controls trigger which action. An action can have any number of keys,
+/// controller buttons and stick directions.
+pub struct Bindings {
+    controls: BTreeMap<Action, Vec<Control>>,
+}
+
+impl Bindings {
+    pub fn default() -> Bindings {
+        let mut controls = BTreeMap::new();
+        controls.insert(
+            Action::MoveLeft,
+            vec![
+                key(Keycode::Left),
+                button(Button::DPadLeft),
+                Control::StickLeft,
+            ],
+        );
+        controls.insert(
+            Action::MoveRight,
+            vec![
+                key(Keycode::Right),
+                button(Button::DPadRight),
+                Control::StickRight,
+            ],
+        );
+        controls.insert(
+            Action::LookUp,
+            vec![key(Keycode::Up), button(Button::DPadUp), Control::StickUp],
+        );
+        controls.insert(
+            Action::LookDown,
+            vec![
+                key(Keycode::Down),
+                button(Button::DPadDown),
+                Control::StickDown,
+            ],
+        );
+        controls.insert(Action::Jump, vec![key(Keycode::Z), button(Button::A)]);
+        controls.insert(
+            Action::Fire,
+            vec![key(Keycode::X), button(Button::X), button(Button::B)],
+        );
+        controls.insert(Action::Pause, vec![key(Keycode::P), button(Button::Start)]);
+        controls.insert(Action::Fullscreen, vec![key(Keycode::F11)]);
+        controls.insert(Action::Debug, vec![key(Keycode::F3)]);
+        controls.insert(Action::Screenshot, vec![key(Keycode::F12)]);
+        controls.insert(Action::Quit, vec![key(Keycode::Escape)]);
+        Bindings { controls: controls }
+    }
+
+    /// Loads bindings from a file with lines like `jump = Z, Space, button:a`.
+    /// Keys are SDL key names, controller buttons SDL button names prefixed with
+    /// `button:` and the left stick `stick:left`, `stick:right`, `stick:up` or
+    /// `stick:down`. Actions not mentioned in the file keep their default
+    /// controls, empty lines and lines starting with `#` are ignored.
+    pub fn load(path: &str) -> Result<Bindings, AssetError> {
+        let text = definitions::read(path)?;
+        Bindings::parse(path, &text)
+    }
+
+    /// Parses the text of a bindings file, `path` is only used in errors.
+    pub fn parse(path: &str, text: &str) -> Result<Bindings, AssetError> {
+        let mut bindings = Bindings::default();
+        for entry in definitions::entries(path, text) {
+            let (number, name, value) = entry?;
+            let error = |message: String| definitions::error(path, number, message);
+            let action =
+                Action::from_name(name).ok_or_else(|| error(format!("unknown action {}", name)))?;
+            let controls = value
+                .split(',')
+                .map(|name| {
+                    let name = name.trim();
+                    parse_control(name).ok_or_else(|| error(format!("unknown control {}", name)))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            bindings.controls.insert(action, controls);
+        }
+
+        Ok(bindings)
+    }
+
+    pub fn controls(&self, action: Action) -> &[Control] {
+        self.controls
+            .get(&action)
+            .map_or(&[], |controls| controls.as_slice())
+    }
+}
+
+fn key(key: Keycode) -> Control {
+    Control::Key(key as i32)
+}
+
+fn button(button: Button) -> Control {
+    Control::Button(button as i32)
+}
+
+fn parse_control(name: &str) -> Option<Control> {
+    if let Some(name) = name.strip_prefix("button:") {
+        Button::from_string(name).map(button)
+    } else if let Some(direction) = name.strip_prefix("stick:") {
+        match direction {
+            "left" => Some(Control::StickLeft),
+            "right" => Some(Control::StickRight),
+            "up" => Some(Control::StickUp),
+            "down" => Some(Control::StickDown),
+            _ => None,
+        }
+    } else {
+        Keycode::from_name(name).map(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binds_several_keys_to_one_action() {
+        let bindings = Bindings::parse("Test.bindings", "jump = Z, Space, Return").unwrap();
+        assert_eq!(
+            bindings.controls(Action::Jump),
+            &[key(Keycode::Z), key(Keycode::Space), key(Keycode::Return)]
+        );
+    }
+
+    #[test]
+    fn binds_buttons_and_stick_directions() {
+        let text = "fire = button:x\nmove_left = stick:left, button:dpleft\n";
+        let bindings = Bindings::parse("Test.bindings", text).unwrap();
+        assert_eq!(bindings.controls(Action::Fire), &[button(Button::X)]);
+        assert_eq!(
+            bindings.controls(Action::MoveLeft),
+            &[Control::StickLeft, button(Button::DPadLeft)]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_actions_and_controls() {
+        let text = "# test bindings\njump = Z\nfly = Space\n";
+        match Bindings::parse("Test.bindings", text) {
+            Err(AssetError::Invalid(location, message)) => {
+                assert_eq!(location, "Test.bindings:3");
+                assert_eq!(message, "unknown action fly");
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        match Bindings::parse("Test.bindings", "jump = Z, stick:sideways") {
+            Err(AssetError::Invalid(location, message)) => {
+                assert_eq!(location, "Test.bindings:1");
+                assert_eq!(message, "unknown control stick:sideways");
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn keeps_defaults_for_unlisted_actions() {
+        let bindings = Bindings::parse("Test.bindings", "jump = Space").unwrap();
+        let defaults = Bindings::default();
+        assert_eq!(bindings.controls(Action::Jump), &[key(Keycode::Space)]);
+        assert_eq!(
+            bindings.controls(Action::Fire),
+            defaults.controls(Action::Fire)
+        );
+        assert_eq!(
+            bindings.controls(Action::MoveLeft),
+            defaults.controls(Action::MoveLeft)
+        );
+    }
+}
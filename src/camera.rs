@@ -0,0 +1,156 @@
+use constants;
+use map::Map;
+use player::{HorizontalFacing, Player, VerticalFacing};
+
+/// The part of the world that is on screen. Positions are the top left of the
+/// screen in world pixels.
+#[derive(Clone, Debug)]
+pub struct Camera {
+    x: i32,
+    y: i32,
+    // top left of the screen before the last update
+    previous_x: i32,
+    previous_y: i32,
+}
+
+impl Camera {
+    /// Creates a camera that is already looking at the player.
+    pub fn new(player: &Player, map: &Map) -> Camera {
+        let (x, y) = target(player, map);
+        Camera {
+            x: x,
+            y: y,
+            previous_x: x,
+            previous_y: y,
+        }
+    }
+
+    /// Moves part of the way towards where the player wants us to look.
+    pub fn update(&mut self, player: &Player, map: &Map) {
+        self.previous_x = self.x;
+        self.previous_y = self.y;
+
+        let (target_x, target_y) = target(player, map);
+        self.x += approach(self.x, target_x);
+        self.y += approach(self.y, target_y);
+    }
+
+    /// The camera as it should be drawn `alpha` of the way to the next update,
+    /// see `Position::interpolate`.
+    pub fn interpolated(&self, alpha: f32) -> Camera {
+        Camera {
+            x: self.previous_x + ((self.x - self.previous_x) as f32 * alpha).round() as i32,
+            y: self.previous_y + ((self.y - self.previous_y) as f32 * alpha).round() as i32,
+            previous_x: self.previous_x,
+            previous_y: self.previous_y,
+        }
+    }
+
+    pub fn to_screen(&self, x: i32, y: i32) -> (i32, i32) {
+        (x - self.x, y - self.y)
+    }
+
+    pub fn left(&self) -> i32 {
+        self.x
+    }
+
+    pub fn top(&self) -> i32 {
+        self.y
+    }
+}
+
+/// Centers on the player, looking ahead in the direction they face, but never
+/// past the edge of the map.
+fn target(player: &Player, map: &Map) -> (i32, i32) {
+    let (center_x, center_y) = player.center();
+    let look_x = match player.horizontal_facing() {
+        HorizontalFacing::Left => -constants::CAMERA_LOOK_AHEAD,
+        HorizontalFacing::Right => constants::CAMERA_LOOK_AHEAD,
+    };
+    let look_y = match player.vertical_facing() {
+        VerticalFacing::Up => -constants::CAMERA_LOOK_VERTICAL,
+        VerticalFacing::Forward => 0,
+        VerticalFacing::Down => constants::CAMERA_LOOK_VERTICAL,
+    };
+
+    let x = center_x + look_x - constants::SCREEN_WIDTH as i32 / 2;
+    let y = center_y + look_y - constants::SCREEN_HEIGHT as i32 / 2;
+    (
+        clamp(x, map.width(), constants::SCREEN_WIDTH as i32),
+        clamp(y, map.height(), constants::SCREEN_HEIGHT as i32),
+    )
+}
+
+/// Keeps the screen inside the map, maps smaller than the screen are centered.
+fn clamp(position: i32, map_size: i32, screen_size: i32) -> i32 {
+    if map_size <= screen_size {
+        (map_size - screen_size) / 2
+    } else {
+        position.max(0).min(map_size - screen_size)
+    }
+}
+
+/// How far to move towards the target in one step. Always moves at least a
+/// pixel so that we actually get there.
+fn approach(current: i32, target: i32) -> i32 {
+    let distance = target - current;
+    let step = distance / constants::CAMERA_SMOOTHING;
+    if step == 0 {
+        distance.signum()
+    } else {
+        step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: i32 = constants::SCREEN_WIDTH as i32;
+    const HEIGHT: i32 = constants::SCREEN_HEIGHT as i32;
+
+    #[test]
+    fn stays_inside_the_map() {
+        // left and top edges
+        assert_eq!(clamp(-50, 2000, WIDTH), 0);
+        assert_eq!(clamp(-50, 1000, HEIGHT), 0);
+        // right and bottom edges
+        assert_eq!(clamp(1500, 2000, WIDTH), 2000 - WIDTH);
+        assert_eq!(clamp(900, 1000, HEIGHT), 1000 - HEIGHT);
+        // anywhere in between is left alone
+        assert_eq!(clamp(300, 2000, WIDTH), 300);
+        assert_eq!(clamp(300, 1000, HEIGHT), 300);
+        assert_eq!(clamp(2000 - WIDTH, 2000, WIDTH), 2000 - WIDTH);
+    }
+
+    #[test]
+    fn centers_maps_smaller_than_the_screen() {
+        for &position in &[-100, 0, 50, 1000] {
+            assert_eq!(clamp(position, 320, WIDTH), -160);
+            assert_eq!(clamp(position, 240, HEIGHT), -120);
+            assert_eq!(clamp(position, WIDTH, WIDTH), 0);
+        }
+    }
+
+    #[test]
+    fn approaches_target_without_overshooting() {
+        for &(start, target) in &[(0, 1), (0, -1), (0, 15), (0, 17), (0, 1000), (500, -300)] {
+            let mut position = start;
+            let mut steps = 0;
+            while position != target {
+                let step = approach(position, target);
+                assert_ne!(step, 0, "stalled at {} on the way to {}", position, target);
+                assert!(
+                    (target - position) * (target - position - step) >= 0,
+                    "overshot {} from {}",
+                    target,
+                    position
+                );
+                position += step;
+                steps += 1;
+                assert!(steps < 1000, "never reached {} from {}", target, start);
+            }
+            assert_eq!(approach(position, target), 0);
+        }
+    }
+}
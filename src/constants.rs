@@ -1,11 +1,54 @@
+use sdl2::pixels::Color;
+use units::{Acceleration, Velocity};
+
 pub const SCREEN_WIDTH: u32 = 640;
 pub const SCREEN_HEIGHT: u32 = 480;
 pub const FPS: u32 = 60;
+pub const UPDATE_STEP: u32 = 10; // milliseconds per simulation step
+pub const MAX_FRAME_TIME: u32 = 250; // milliseconds
 pub const TILE_SIZE: u32 = 32;
-pub const WALKING_ACCELERATION: f32 = 0.0012; // pixels per ms²
-pub const MAX_SPEED: f32 = 0.325; // pixels per ms
-pub const SLOW_DOWN: f32 = 0.8;
-pub const JUMP_SPEED: f32 = 0.325;
+// sprite sheets from the original game use black for transparency
+pub const TRANSPARENT_COLOR: Color = Color {
+    r: 0,
+    g: 0,
+    b: 0,
+    a: 0xff,
+};
+pub const CAMERA_LOOK_AHEAD: i32 = 64; // pixels
+pub const CAMERA_LOOK_VERTICAL: i32 = 96; // pixels
+pub const CAMERA_SMOOTHING: i32 = 16; // moves 1/16th of the way per update step
+pub const STAGE_DIRECTORY: &str = "content/Stage";
+pub const FONT: &str = "content/Font.fnt";
+pub const PLAYER_ANIMATIONS: &str = "content/MyChar.anim";
+pub const ARMS_SHEET: &str = "content/Arms.bmp";
+pub const BULLET_SHEET: &str = "content/Bullet.bmp";
+pub const CARET_SHEET: &str = "content/Caret.bmp";
+pub const CRYSTAL_SHEET: &str = "content/Crystal.bmp";
+pub const BINDINGS_FILE: &str = "keybindings.cfg";
+// written next to the bindings and replays, in the directory the game runs in
+pub const LOG_FILE: &str = "cavestory.log";
+pub const SCREENSHOT_DIRECTORY: &str = "screenshots";
+pub const FRAME_DUMP_DIRECTORY: &str = "frames";
+// environment variable with the log filter, `--log` takes precedence
+pub const LOG_VARIABLE: &str = "CAVESTORY_LOG";
+pub const CONTROLLER_DEAD_ZONE: i16 = 8000;
+// velocities are in 1/512 pixels per update step, accelerations in 1/512
+// pixels per update step²
+pub const WALKING_ACCELERATION: Acceleration = Acceleration::new(61);
+pub const MAX_SPEED: Velocity = Velocity::new(1664);
+pub const SLOW_DOWN: (i32, i32) = (4, 5); // fraction of speed kept every update step
+pub const JUMP_SPEED: Velocity = Velocity::new(1664);
 pub const JUMP_TIME: u32 = 275; // milliseconds
-pub const GRAVITY: f32 = 0.0012;
-pub const MAX_SPEED_Y: f32 = 0.325;
+pub const GRAVITY: Acceleration = Acceleration::new(61);
+pub const MAX_SPEED_Y: Velocity = Velocity::new(1664);
+pub const PLAYER_MAX_HEALTH: u32 = 3;
+pub const HAZARD_DAMAGE: u32 = 1;
+pub const INVINCIBILITY_TIME: u32 = 3000; // milliseconds after a hit
+pub const INVINCIBILITY_FLASH_TIME: u32 = 50; // milliseconds shown or hidden
+// getting hit throws the player up and away from what hit them
+pub const KNOCKBACK_SPEED_X: Velocity = Velocity::new(768);
+pub const KNOCKBACK_SPEED_Y: Velocity = Velocity::new(1024);
+pub const PROJECTILE_SPEED: Velocity = Velocity::new(4096);
+pub const CRYSTAL_EXPERIENCE: u32 = 5;
+pub const EXPERIENCE_LOST_PER_DAMAGE: u32 = 2;
+pub const LEVEL_MESSAGE_TIME: u32 = 1000; // milliseconds
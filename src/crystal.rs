@@ -0,0 +1,76 @@
+use camera::Camera;
+use constants;
+use graphics::{AssetError, Depth, Graphics, Layer};
+use rectangle::Rectangle;
+use sdl2::rect::Rect;
+use sprite::{AnimatedSprite, AnimationMode, Drawable, Frame, Updatable};
+use units::Milliseconds;
+
+const FRAMES: i32 = 4;
+const FRAME_TIME: u32 = 80; // milliseconds
+
+// half the size of the square around the crystal's center that is picked up
+const PICKUP_SIZE: i32 = 8;
+
+/// A spinning crystal that gives the player's weapon experience when touched.
+pub struct Crystal<'a> {
+    sprite: AnimatedSprite<'a>,
+    // center in world pixels
+    x: i32,
+    y: i32,
+    experience: u32,
+}
+
+impl<'a> Crystal<'a> {
+    pub fn new(
+        graphics: &mut Graphics<'a>,
+        x: i32,
+        y: i32,
+        experience: u32,
+    ) -> Result<Crystal<'a>, AssetError> {
+        let tile_size = constants::TILE_SIZE;
+        let frames = (0..FRAMES)
+            .map(|index| {
+                Frame::new(
+                    Rect::new(index * tile_size as i32, 0, tile_size, tile_size),
+                    Milliseconds::new(FRAME_TIME),
+                )
+            })
+            .collect();
+        Ok(Crystal {
+            sprite: AnimatedSprite::new(
+                graphics,
+                constants::CRYSTAL_SHEET,
+                frames,
+                AnimationMode::Loop,
+            )?,
+            x: x,
+            y: y,
+            experience: experience,
+        })
+    }
+
+    pub fn update(&mut self, elapsed_time: Milliseconds) {
+        self.sprite.update(elapsed_time);
+    }
+
+    pub fn pickup_box(&self) -> Rectangle {
+        Rectangle::new(
+            self.x - PICKUP_SIZE,
+            self.y - PICKUP_SIZE,
+            2 * PICKUP_SIZE,
+            2 * PICKUP_SIZE,
+        )
+    }
+
+    pub fn experience(&self) -> u32 {
+        self.experience
+    }
+
+    pub fn draw(&self, graphics: &mut Graphics<'a>, camera: &Camera) {
+        let half_tile = constants::TILE_SIZE as i32 / 2;
+        let (x, y) = camera.to_screen(self.x - half_tile, self.y - half_tile);
+        self.sprite
+            .draw(graphics, Depth::new(Layer::Entities, 0), x, y);
+    }
+}
@@ -0,0 +1,92 @@
+use entities::Entities;
+use graphics::{Depth, Graphics, Layer};
+use sdl2::pixels::Color;
+use std::time::Duration;
+
+// how often the frame rate shown is recalculated
+const FPS_INTERVAL: u64 = 500; // milliseconds
+
+/// Frame timing and player state drawn on top of the game, toggled with the
+/// debug action.
+pub struct DebugOverlay {
+    enabled: bool,
+    fps: f32,
+    // frames and time since the frame rate was last calculated
+    frames: u32,
+    frames_time: Duration,
+    update_time: Duration,
+    draw_time: Duration,
+    overshoots: u32,
+}
+
+impl DebugOverlay {
+    pub fn new() -> DebugOverlay {
+        DebugOverlay {
+            enabled: false,
+            fps: 0.0,
+            frames: 0,
+            frames_time: Duration::new(0, 0),
+            update_time: Duration::new(0, 0),
+            draw_time: Duration::new(0, 0),
+            overshoots: 0,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    /// Keeps track of how long a frame took in total and how much of that was
+    /// spent updating and drawing.
+    pub fn record_frame(
+        &mut self,
+        elapsed_time: Duration,
+        update_time: Duration,
+        draw_time: Duration,
+    ) {
+        self.update_time = update_time;
+        self.draw_time = draw_time;
+        self.frames += 1;
+        self.frames_time += elapsed_time;
+        if self.frames_time >= Duration::from_millis(FPS_INTERVAL) {
+            self.fps = self.frames as f32 / seconds(self.frames_time);
+            self.frames = 0;
+            self.frames_time = Duration::new(0, 0);
+        }
+    }
+
+    /// Counts a frame that took longer than it should have.
+    pub fn record_overshoot(&mut self) {
+        self.overshoots += 1;
+    }
+
+    pub fn draw<'a>(&self, graphics: &mut Graphics<'a>, entities: &Entities<'a>, alpha: f32) {
+        if !self.enabled {
+            return;
+        }
+        entities.draw_debug(graphics, alpha);
+
+        let text = format!(
+            "fps: {:.1}\n\
+             update: {:.2} ms, draw: {:.2} ms\n\
+             overshot frames: {}\n\
+             {}",
+            self.fps,
+            seconds(self.update_time) * 1000.0,
+            seconds(self.draw_time) * 1000.0,
+            self.overshoots,
+            entities.debug_text()
+        );
+        graphics.draw_text(
+            &text,
+            8,
+            8,
+            Color::RGB(0xff, 0xff, 0),
+            Depth::new(Layer::Hud, 1),
+        );
+    }
+}
+
+fn seconds(duration: Duration) -> f32 {
+    duration.as_secs() as f32 + duration.subsec_nanos() as f32 / 1_000_000_000.0
+}
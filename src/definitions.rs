@@ -0,0 +1,73 @@
+use graphics::AssetError;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Reads a definition file, the text files fonts, animations and key bindings
+/// are described in.
+pub fn read(path: &str) -> Result<String, AssetError> {
+    if !Path::new(path).exists() {
+        return Err(AssetError::Missing(path.to_string()));
+    }
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|error| AssetError::Invalid(path.to_string(), error.to_string()))?;
+    Ok(text)
+}
+
+/// The `key = value` lines of a definition file as `(line number, key, value)`
+/// with both sides trimmed and lines numbered from one. Empty lines and lines
+/// starting with `#` are skipped, any other line without `=` is an error.
+pub fn entries<'t>(
+    path: &'t str,
+    text: &'t str,
+) -> impl Iterator<Item = Result<(usize, &'t str, &'t str), AssetError>> + 't {
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|&(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(move |(number, line)| {
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            match parts.next() {
+                Some(value) => Ok((number, key, value.trim())),
+                None => Err(error(path, number, "expected `key = value`".to_string())),
+            }
+        })
+}
+
+/// An error on the given line of a definition file, reported as `path:line`.
+pub fn error(path: &str, number: usize, message: String) -> AssetError {
+    AssetError::Invalid(format!("{}:{}", path, number), message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_entries() {
+        let text = "# comment\n\n  sheet = a.bmp \nwidths=1 2\nempty =\n";
+        let entries = entries("Test.def", text)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                (3, "sheet", "a.bmp"),
+                (4, "widths", "1 2"),
+                (5, "empty", "")
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_lines_without_value() {
+        let text = "sheet = a.bmp\n# comment\nsheet\n";
+        match entries("Test.def", text).nth(1) {
+            Some(Err(AssetError::Invalid(location, _))) => assert_eq!(location, "Test.def:3"),
+            other => panic!("unexpected entry: {:?}", other),
+        }
+    }
+}
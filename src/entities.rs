@@ -1,19 +1,45 @@
-use graphics::Graphics;
+use bindings::Action;
+use camera::Camera;
+use constants;
+use crystal::Crystal;
+use graphics::{AssetError, Graphics};
+use hud::Hud;
 use input::Input;
+use map::Map;
 use player::Player;
+use projectile::{Flight, Impact, Projectile};
 use units::Milliseconds;
-
-use sdl2::keyboard::Keycode;
+use weapon::LevelChange;
 
 pub struct Entities<'a> {
     player: Player<'a>,
+    map: Map<'a>,
+    camera: Camera,
+    projectiles: Vec<Projectile<'a>>,
+    impacts: Vec<Impact<'a>>,
+    crystals: Vec<Crystal<'a>>,
+    hud: Hud,
 }
 
 impl<'a> Entities<'a> {
-    pub fn new(graphics: &mut Graphics<'a>) -> Entities<'a> {
-        Entities {
-            player: Player::new(graphics, 320, 240),
-        }
+    pub fn new(graphics: &mut Graphics<'a>, map: Map<'a>) -> Result<Entities<'a>, AssetError> {
+        // on the floor under the floating platform of the test map
+        let player = Player::new(graphics, 320, 320)?;
+        let camera = Camera::new(&player, &map);
+        let crystals = map
+            .crystals()
+            .iter()
+            .map(|&(x, y)| Crystal::new(graphics, x, y, constants::CRYSTAL_EXPERIENCE))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Entities {
+            player: player,
+            map: map,
+            camera: camera,
+            projectiles: Vec::new(),
+            impacts: Vec::new(),
+            crystals: crystals,
+            hud: Hud::new(),
+        })
     }
 
     pub fn process_input(&mut self, input: &Input) {
@@ -26,8 +52,8 @@ impl<'a> Entities<'a> {
         //  else
         //    stop moving
         match (
-            input.key_held(Keycode::Left),
-            input.key_held(Keycode::Right),
+            input.action_held(Action::MoveLeft),
+            input.action_held(Action::MoveRight),
         ) {
             (true, true) => self.player.stop_moving(),
             (false, false) => self.player.stop_moving(),
@@ -36,8 +62,8 @@ impl<'a> Entities<'a> {
         }
 
         match (
-            input.key_held(Keycode::Up),
-            input.key_held(Keycode::Down),
+            input.action_held(Action::LookUp),
+            input.action_held(Action::LookDown),
         ) {
             (true, true) => self.player.look_horizontal(),
             (false, false) => self.player.look_horizontal(),
@@ -45,20 +71,105 @@ impl<'a> Entities<'a> {
             (false, true) => self.player.look_down(),
         }
 
-        if input.key_pressed(Keycode::Z) {
+        if input.action_pressed(Action::Jump) {
             self.player.start_jump();
-        } else if input.key_released(Keycode::Z) {
+        } else if input.action_released(Action::Jump) {
             self.player.stop_jump();
         }
+
+        if input.action_pressed(Action::Fire) {
+            if let Some(projectile) = self.player.fire(self.projectiles.len()) {
+                self.projectiles.push(projectile);
+            }
+        }
+    }
+
+    /// `alpha` is how far we are between the last and the next update, from 0 to 1.
+    pub fn draw(&self, graphics: &mut Graphics<'a>, alpha: f32) {
+        let camera = self.camera.interpolated(alpha);
+        self.map.draw(graphics, &camera);
+        for crystal in &self.crystals {
+            crystal.draw(graphics, &camera);
+        }
+        self.player.draw(graphics, &camera, alpha);
+        for projectile in &self.projectiles {
+            projectile.draw(graphics, &camera, alpha);
+        }
+        for impact in &self.impacts {
+            impact.draw(graphics, &camera);
+        }
+        self.hud.draw(graphics, self.player.weapon().experience());
+    }
+
+    /// Draws collision boxes on top of everything.
+    pub fn draw_debug(&self, graphics: &mut Graphics<'a>, alpha: f32) {
+        let camera = self.camera.interpolated(alpha);
+        self.player.draw_collision_boxes(graphics, &camera, alpha);
+    }
+
+    /// Whether the game is over, nothing moves anymore once the player died.
+    pub fn player_dead(&self) -> bool {
+        self.player.is_dead()
     }
 
-    pub fn draw(&self, graphics: &mut Graphics) {
-        graphics.clear();
-        self.player.draw(graphics);
-        graphics.present();
+    pub fn debug_text(&self) -> String {
+        format!(
+            "{}\nprojectiles: {}, impacts: {}",
+            self.player.debug_text(),
+            self.projectiles.len(),
+            self.impacts.len()
+        )
     }
 
     pub fn update(&mut self, elapsed_time: Milliseconds) {
-        self.player.update(elapsed_time);
+        if self.player_dead() {
+            return;
+        }
+        let hurt = self.player.update(elapsed_time, &self.map);
+        self.level_changed(hurt);
+        self.camera.update(&self.player, &self.map);
+
+        // crystals are picked up by touching them
+        let mut collected = 0;
+        let hit_box = self.player.hit_box();
+        self.crystals.retain(|crystal| {
+            if crystal.pickup_box().intersects(&hit_box) {
+                collected += crystal.experience();
+                false
+            } else {
+                true
+            }
+        });
+        if collected > 0 {
+            let change = self.player.collect_experience(collected);
+            self.level_changed(change);
+        }
+        for crystal in &mut self.crystals {
+            crystal.update(elapsed_time);
+        }
+
+        let mut flying = Vec::with_capacity(self.projectiles.len());
+        for mut projectile in self.projectiles.drain(..) {
+            match projectile.update(&self.map) {
+                Flight::Flying => flying.push(projectile),
+                Flight::Expired => (),
+                Flight::HitWall(x, y) => self.impacts.push(self.player.weapon().impact(x, y)),
+            }
+        }
+        self.projectiles = flying;
+
+        for impact in &mut self.impacts {
+            impact.update(elapsed_time);
+        }
+        self.impacts.retain(|impact| !impact.finished());
+        self.hud.update(elapsed_time);
+    }
+
+    /// Shows a change of the weapon's level on the HUD.
+    fn level_changed(&mut self, change: Option<LevelChange>) {
+        if let Some(change) = change {
+            info!("Weapon level changed: {:?}", change);
+            self.hud.level_changed(change);
+        }
     }
 }
@@ -0,0 +1,182 @@
+use definitions;
+use graphics::{AssetError, Graphics};
+use sdl2::rect::Rect;
+use sdl2::render::Texture;
+
+/// Where the glyphs of a bitmap font are on its sheet and how wide they are.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FontMetrics {
+    pub sheet: String,
+    pub cell_width: u32,
+    pub cell_height: u32,
+    pub columns: u32,
+    pub first: u32,
+    pub widths: Vec<u32>,
+}
+
+impl FontMetrics {
+    /// Parses a font file with the settings `sheet = <image>`,
+    /// `cell = <width> <height>`, `columns = <cells per row>`,
+    /// `first = <character code of the first cell>` and
+    /// `widths = <advance of every character> ...`. Empty lines and lines
+    /// starting with `#` are ignored.
+    pub fn parse(path: &str, text: &str) -> Result<FontMetrics, AssetError> {
+        let mut sheet = None;
+        let mut cell = None;
+        let mut columns = None;
+        let mut first = None;
+        let mut widths = None;
+        for entry in definitions::entries(path, text) {
+            let (number, name, value) = entry?;
+            let error = |message: String| definitions::error(path, number, message);
+            let numbers = || {
+                value
+                    .split_whitespace()
+                    .map(|number| number.parse::<u32>().ok())
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| error(format!("expected numbers for {}", name)))
+            };
+            match name {
+                "sheet" => sheet = Some(value.to_string()),
+                "cell" => match numbers()?[..] {
+                    [width, height] if width > 0 && height > 0 => cell = Some((width, height)),
+                    _ => return Err(error("expected `cell = width height`".to_string())),
+                },
+                "columns" => match numbers()?[..] {
+                    [count] if count > 0 => columns = Some(count),
+                    _ => return Err(error("expected `columns = count`".to_string())),
+                },
+                "first" => match numbers()?[..] {
+                    [code] => first = Some(code),
+                    _ => return Err(error("expected `first = character code`".to_string())),
+                },
+                "widths" => widths = Some(numbers()?),
+                _ => return Err(error(format!("unknown setting {}", name))),
+            }
+        }
+
+        let missing =
+            |setting: &str| AssetError::Invalid(path.to_string(), format!("no {} given", setting));
+        let (cell_width, cell_height) = cell.ok_or_else(|| missing("cell"))?;
+        Ok(FontMetrics {
+            sheet: sheet.ok_or_else(|| missing("sheet"))?,
+            cell_width: cell_width,
+            cell_height: cell_height,
+            columns: columns.ok_or_else(|| missing("columns"))?,
+            first: first.ok_or_else(|| missing("first"))?,
+            widths: widths.ok_or_else(|| missing("widths"))?,
+        })
+    }
+
+    /// Where a character is on the sheet, `None` for characters the font
+    /// doesn't have. The rectangle is as wide as the character advances.
+    pub fn glyph(&self, character: char) -> Option<Rect> {
+        let index = (character as u32).checked_sub(self.first)?;
+        let width = *self.widths.get(index as usize)?;
+        Some(Rect::new(
+            ((index % self.columns) * self.cell_width) as i32,
+            ((index / self.columns) * self.cell_height) as i32,
+            width.max(1),
+            self.cell_height,
+        ))
+    }
+
+    /// Width in pixels of the longest line of `text`.
+    pub fn text_width(&self, text: &str) -> u32 {
+        text.lines()
+            .map(|line| {
+                line.chars()
+                    .filter_map(|character| self.glyph(self.fallback(character)))
+                    .map(|glyph| glyph.width())
+                    .sum()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The character drawn in place of `character`, a question mark for
+    /// characters the font doesn't have.
+    pub fn fallback(&self, character: char) -> char {
+        if self.glyph(character).is_some() {
+            character
+        } else {
+            '?'
+        }
+    }
+}
+
+/// A bitmap font, the glyphs are cut from a sprite sheet. The sheet isn't
+/// shared through the texture cache, because it is tinted for every glyph.
+pub struct Font<'a> {
+    pub metrics: FontMetrics,
+    pub sheet: Texture<'a>,
+}
+
+impl<'a> Font<'a> {
+    pub fn load(graphics: &mut Graphics<'a>, path: &str) -> Result<Font<'a>, AssetError> {
+        let text = definitions::read(path)?;
+        let metrics = FontMetrics::parse(path, &text)?;
+        let sheet = graphics.load_texture(&metrics.sheet)?;
+        Ok(Font {
+            metrics: metrics,
+            sheet: sheet,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FONT: &str = "# test font\n\
+                        sheet = content/Font.bmp\n\
+                        cell = 8 10\n\
+                        columns = 2\n\
+                        first = 63\n\
+                        widths = 6 7 8\n";
+
+    #[test]
+    fn parses_font() {
+        let metrics = FontMetrics::parse("Test.fnt", FONT).unwrap();
+        assert_eq!(metrics.sheet, "content/Font.bmp");
+        assert_eq!((metrics.cell_width, metrics.cell_height), (8, 10));
+        assert_eq!(metrics.columns, 2);
+        assert_eq!(metrics.first, 63);
+        assert_eq!(metrics.widths, vec![6, 7, 8]);
+    }
+
+    #[test]
+    fn finds_glyphs() {
+        let metrics = FontMetrics::parse("Test.fnt", FONT).unwrap();
+        assert_eq!(metrics.glyph('?'), Some(Rect::new(0, 0, 6, 10)));
+        assert_eq!(metrics.glyph('@'), Some(Rect::new(8, 0, 7, 10)));
+        assert_eq!(metrics.glyph('A'), Some(Rect::new(0, 10, 8, 10)));
+        assert_eq!(metrics.glyph('B'), None);
+        assert_eq!(metrics.glyph(' '), None);
+    }
+
+    #[test]
+    fn measures_text() {
+        let metrics = FontMetrics::parse("Test.fnt", FONT).unwrap();
+        assert_eq!(metrics.text_width("A@"), 15);
+        // unknown characters are drawn as question marks
+        assert_eq!(metrics.text_width("AZ"), 14);
+        assert_eq!(metrics.text_width("?\nAAA"), 24);
+        assert_eq!(metrics.text_width(""), 0);
+    }
+
+    #[test]
+    fn reports_missing_settings() {
+        match FontMetrics::parse("Test.fnt", "sheet = a.bmp\ncell = 8 8") {
+            Err(AssetError::Invalid(location, message)) => {
+                assert_eq!(location, "Test.fnt");
+                assert_eq!(message, "no columns given");
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        match FontMetrics::parse("Test.fnt", "cell = 8") {
+            Err(AssetError::Invalid(location, _)) => assert_eq!(location, "Test.fnt:1"),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -1,104 +1,364 @@
+use bindings::{Action, Bindings};
 use constants;
+use debug::DebugOverlay;
 use entities::Entities;
-use graphics::Graphics;
-use input::Input;
+use graphics::{Depth, Graphics, Layer};
+use input::{Input, InputEvent};
+use map::Map;
+use options::Options;
+use replay::{FrameInput, Recorder, Replay};
 
 use sdl2;
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::controller::GameController;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::pixels::Color;
+use stage::Stage;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
 use std::time::{Duration, Instant};
+use timestep::FixedTimestep;
 use units::Milliseconds;
 
 pub struct Game {
     context: sdl2::Sdl,
+    // PNG decoding stays available for as long as this is alive
+    _image_context: sdl2::image::Sdl2ImageContext,
     event_pump: sdl2::EventPump,
+    options: Options,
+    input: Input,
+    controller_subsystem: Option<sdl2::GameControllerSubsystem>,
+    controllers: Vec<GameController>,
+    recorder: Option<Recorder>,
+    replay: Option<Replay>,
 }
 
 impl Game {
-    pub fn new() -> Result<Game, String> {
+    pub fn new(options: Options) -> Result<Game, String> {
         let context = sdl2::init()?;
         let mut event_pump = context.event_pump()?;
         event_pump.disable_event(sdl2::event::EventType::MouseMotion);
-        event_pump.disable_event(sdl2::event::EventType::Window);
+        let image_context = sdl2::image::init(sdl2::image::INIT_PNG)?;
+
+        // controllers are opened as they are plugged in, SDL reports the ones that
+        // are already connected as being plugged in at startup
+        let controller_subsystem = match context.game_controller() {
+            Ok(subsystem) => Some(subsystem),
+            Err(error) => {
+                warn!("Could not initialize game controllers: {}", error);
+                None
+            }
+        };
+
+        // a missing bindings file is only an error if it was asked for explicitly
+        let bindings = match options.bindings {
+            Some(ref path) => Bindings::load(path).map_err(|error| error.to_string())?,
+            None if Path::new(constants::BINDINGS_FILE).exists() => {
+                Bindings::load(constants::BINDINGS_FILE).map_err(|error| error.to_string())?
+            }
+            None => Bindings::default(),
+        };
+        let recorder = match options.record {
+            Some(ref path) => {
+                Some(Recorder::create(path).map_err(|error| format!("{}: {}", path, error))?)
+            }
+            None => None,
+        };
+        let replay = match options.replay {
+            Some(ref path) => Some(Replay::load(path)?),
+            None => None,
+        };
 
         Ok(Game {
             context: context,
+            _image_context: image_context,
             event_pump: event_pump,
+            options: options,
+            input: Input::new(bindings),
+            controller_subsystem: controller_subsystem,
+            controllers: Vec::new(),
+            recorder: recorder,
+            replay: replay,
         })
     }
 
     pub fn event_loop(&mut self) {
-        match Graphics::load_canvas(&self.context) {
-            Ok(mut canvas) => {
-                let mut texture_creator = canvas.texture_creator();
-                let mut graphics = Graphics::new(&mut canvas, &mut texture_creator);
-
-                let mut entities = Entities::new(&mut graphics);
-
-                // target duration for one frame
-                // A bit lower than actually needed to provide some wriggle room for thread::sleep
-                let target_duration = Duration::new(0, 1_000_000_000 / constants::FPS);
-
-                let mut running = true;
-                let mut start_time = ::std::time::Instant::now();
-                let mut last_update_time = start_time;
-                let mut input = Input::new();
-
-                while running {
-                    input.begin_new_frame();
-
-                    // handle input
-                    for event in self.event_pump.poll_iter() {
-                        match event {
-                            Event::Quit { .. } => {
-                                running = false;
-                            }
-                            Event::KeyDown {
-                                keycode: Some(code),
-                                ..
-                            } => {
-                                input.on_key_down(code);
-                            }
-                            Event::KeyUp {
-                                keycode: Some(code),
-                                ..
-                            } => {
-                                input.on_key_up(code);
+        if self.options.headless {
+            match Graphics::load_software_canvas() {
+                Ok(mut canvas) => {
+                    let texture_creator = canvas.texture_creator();
+                    let mut graphics = Graphics::new(&mut canvas, &texture_creator);
+                    if let Err(error) = self.run(&mut graphics) {
+                        error!("Could not load game content: {}", error);
+                    }
+                }
+                Err(error) => {
+                    error!("Could not initialize graphics: {}", error);
+                }
+            }
+        } else {
+            match Graphics::load_canvas(&self.context, self.options.scale) {
+                Ok(mut canvas) => {
+                    let texture_creator = canvas.texture_creator();
+                    let mut graphics = Graphics::new(&mut canvas, &texture_creator);
+                    if let Err(error) = self.run(&mut graphics) {
+                        error!("Could not load game content: {}", error);
+                    }
+                }
+                Err(error) => {
+                    error!("Could not initialize graphics: {}", error);
+                }
+            }
+        }
+    }
+
+    fn run<'a>(&mut self, graphics: &mut Graphics<'a>) -> Result<(), Box<Error>> {
+        graphics.set_color_key(Some(constants::TRANSPARENT_COLOR));
+        // development builds keep going with placeholders for missing art
+        graphics.set_missing_texture_fallback(cfg!(debug_assertions));
+        graphics.resize();
+        graphics.load_font(constants::FONT)?;
+
+        if self.options.dump_frames.is_some() {
+            fs::create_dir_all(constants::FRAME_DUMP_DIRECTORY)?;
+        }
+
+        let map = self.load_map(graphics)?;
+        let mut entities = Entities::new(graphics, map)?;
+
+        // target duration for one frame
+        // A bit lower than actually needed to provide some wriggle room for thread::sleep
+        let target_duration = Duration::new(0, 1_000_000_000 / constants::FPS);
+
+        let mut running = true;
+        let mut start_time = ::std::time::Instant::now();
+        let mut last_update_time = start_time;
+        let mut paused = false;
+        let mut timestep = FixedTimestep::new(
+            Milliseconds::new(constants::UPDATE_STEP),
+            Milliseconds::new(constants::MAX_FRAME_TIME),
+        );
+        let mut frame_count = 0;
+        let mut debug = DebugOverlay::new();
+
+        while running {
+            self.input.begin_new_frame();
+
+            // handle input
+            let mut frame = FrameInput {
+                elapsed_time: Duration::new(0, 0),
+                events: Vec::new(),
+            };
+            for event in self.event_pump.poll_iter() {
+                match event {
+                    Event::Quit { .. } => {
+                        running = false;
+                    }
+                    Event::Window {
+                        win_event: WindowEvent::SizeChanged(..),
+                        ..
+                    } => {
+                        graphics.resize();
+                    }
+                    Event::KeyDown {
+                        keycode: Some(code),
+                        ..
+                    } => {
+                        frame.events.push(InputEvent::KeyDown(code));
+                    }
+                    Event::KeyUp {
+                        keycode: Some(code),
+                        ..
+                    } => {
+                        frame.events.push(InputEvent::KeyUp(code));
+                    }
+                    Event::ControllerDeviceAdded { which, .. } => {
+                        if let Some(ref subsystem) = self.controller_subsystem {
+                            match subsystem.open(which) {
+                                Ok(controller) => self.controllers.push(controller),
+                                Err(error) => warn!("Could not open controller: {}", error),
                             }
-                            _ => (),
                         }
                     }
+                    Event::ControllerDeviceRemoved { which, .. } => {
+                        self.controllers
+                            .retain(|controller| controller.instance_id() != which);
+                        frame.events.push(InputEvent::ControllerRemoved);
+                    }
+                    Event::ControllerButtonDown { button, .. } => {
+                        frame.events.push(InputEvent::ButtonDown(button));
+                    }
+                    Event::ControllerButtonUp { button, .. } => {
+                        frame.events.push(InputEvent::ButtonUp(button));
+                    }
+                    Event::ControllerAxisMotion { axis, value, .. } => {
+                        frame.events.push(InputEvent::AxisMotion(axis, value));
+                    }
+                    _ => (),
+                }
+            }
 
-                    if input.key_pressed(Keycode::Escape) {
-                        running = false;
+            // without a display there is nothing to keep pace with, so headless
+            // runs pretend every frame took exactly as long as it should
+            let current_time = if self.options.headless {
+                last_update_time + target_duration
+            } else {
+                Instant::now()
+            };
+            frame.elapsed_time = current_time - last_update_time;
+            last_update_time = current_time;
+
+            // a replay overrides both the input and the timing of the frame
+            if let Some(ref mut replay) = self.replay {
+                match replay.next_frame() {
+                    Some(replayed) => frame = replayed,
+                    None => {
+                        info!("Replay finished after {} frames", frame_count);
+                        break;
                     }
+                }
+            }
+            if let Some(ref mut recorder) = self.recorder {
+                if let Err(error) = recorder.record(&frame) {
+                    error!("Could not record input: {}", error);
+                }
+            }
 
-                    entities.process_input(&input);
+            for event in frame.events {
+                self.input.handle_event(event);
+            }
 
-                    // handle timer callbacks
+            if self.input.action_pressed(Action::Quit) {
+                running = false;
+            }
+            if self.input.action_pressed(Action::Pause) {
+                paused = !paused;
+            }
+            if self.input.action_pressed(Action::Fullscreen) {
+                graphics.toggle_fullscreen();
+            }
+            if self.input.action_pressed(Action::Debug) {
+                debug.toggle();
+            }
+            if self.input.action_pressed(Action::Screenshot) {
+                match next_screenshot_path() {
+                    Ok(path) => {
+                        info!("Saving screenshot to {}", path);
+                        graphics.save_screenshot(&path);
+                    }
+                    Err(error) => error!("Could not save screenshot: {}", error),
+                }
+            }
 
-                    // update. move player, projectiles, check collisions
-                    let current_time = Instant::now();
-                    entities.update(Milliseconds::from_duration(current_time - last_update_time));
-                    last_update_time = current_time;
+            let update_start = Instant::now();
+            if entities.player_dead() {
+                // the game stands still on the game over screen until the
+                // player starts over
+                if self.input.action_pressed(Action::Jump) {
+                    info!("Starting over after the player died");
+                    let map = self.load_map(graphics)?;
+                    entities = Entities::new(graphics, map)?;
+                }
+            } else if !paused {
+                entities.process_input(&self.input);
 
-                    // draw EVERYTHING
-                    entities.draw(&mut graphics);
+                // handle timer callbacks
 
-                    let frame_end = sync_duration(start_time, target_duration);
+                // update. move player, projectiles, check collisions
+                timestep.accumulate(frame.elapsed_time);
+                while timestep.tick() {
+                    entities.update(timestep.step());
+                }
+            }
 
-                    // println!("fps: {:.4}", 1_000_000_000.0 / (frame_end - start_time).subsec_nanos() as f64);
-                    start_time = frame_end;
+            // draw EVERYTHING
+            let draw_start = Instant::now();
+            graphics.clear();
+            entities.draw(graphics, timestep.alpha());
+            debug.draw(graphics, &entities, timestep.alpha());
+            if entities.player_dead() {
+                draw_centered_text(graphics, "GAME OVER", constants::SCREEN_HEIGHT as i32 / 3);
+                draw_centered_text(
+                    graphics,
+                    "Press jump to try again",
+                    constants::SCREEN_HEIGHT as i32 / 3 + 24,
+                );
+            } else if paused {
+                draw_centered_text(graphics, "PAUSED", constants::SCREEN_HEIGHT as i32 / 3);
+            }
+            if let Some(every) = self.options.dump_frames {
+                if frame_count % every == 0 {
+                    graphics.save_screenshot(&format!(
+                        "{}/frame{:06}.bmp",
+                        constants::FRAME_DUMP_DIRECTORY,
+                        frame_count
+                    ));
                 }
             }
-            Err(error) => {
-                println!("Could not initialize graphics: {}", error);
+            graphics.present();
+            debug.record_frame(
+                frame.elapsed_time,
+                draw_start - update_start,
+                draw_start.elapsed(),
+            );
+
+            frame_count += 1;
+            if self
+                .options
+                .frames
+                .is_some_and(|frames| frame_count >= frames)
+            {
+                running = false;
+            }
+
+            if !self.options.headless {
+                let (frame_end, overshot) = sync_duration(start_time, target_duration);
+                if overshot {
+                    debug!("Frame {} overshot its target duration", frame_count);
+                    debug.record_overshoot();
+                }
+                start_time = frame_end;
+            }
+        }
+
+        if self.options.headless {
+            // give automated runs something to compare against
+            match graphics.read_frame() {
+                Ok(frame) => info!(
+                    "Rendered {} frames, last frame checksum {:016x}",
+                    frame_count,
+                    checksum(&frame.pixels)
+                ),
+                Err(error) => error!("Could not read back frame: {}", error),
             }
         }
+
+        Ok(())
+    }
+
+    fn load_map<'a>(&self, graphics: &mut Graphics<'a>) -> Result<Map<'a>, Box<Error>> {
+        match self.options.stage {
+            Some((ref stage, ref tileset)) => {
+                let stage_data = Stage::load(constants::STAGE_DIRECTORY, stage, tileset)?;
+                info!(
+                    "Loaded stage {}: {}x{} tiles, {} entities",
+                    stage,
+                    stage_data.layout.width,
+                    stage_data.layout.height,
+                    stage_data.entities.len()
+                );
+                let tileset_image = format!("{}/Prt{}.bmp", constants::STAGE_DIRECTORY, tileset);
+                Ok(Map::from_stage(graphics, &stage_data, &tileset_image)?)
+            }
+            None => Ok(Map::create_test_map(graphics)?),
+        }
     }
 }
 
-fn sync_duration(frame_start: Instant, target_duration: Duration) -> Instant {
+/// Waits until the frame has taken `target_duration`. Returns when that was and
+/// whether the frame took longer than it should have.
+fn sync_duration(frame_start: Instant, target_duration: Duration) -> (Instant, bool) {
     let approximate_duration = target_duration - Duration::new(0, 1_200_000);
     let mut current_time = Instant::now();
     let elapsed_time = current_time - frame_start;
@@ -112,11 +372,42 @@ fn sync_duration(frame_start: Instant, target_duration: Duration) -> Instant {
             ::std::thread::sleep(Duration::new(0, 10));
             current_time = Instant::now();
         }
-    } else {
-        println!(
-            "overshot frame target. Duration: {:?}",
-            elapsed_time - target_duration
-        );
     }
-    current_time
+    (current_time, elapsed_time > target_duration)
+}
+
+/// The first numbered screenshot file that doesn't exist yet.
+fn next_screenshot_path() -> io::Result<String> {
+    fs::create_dir_all(constants::SCREENSHOT_DIRECTORY)?;
+    let path = (1..)
+        .map(|number| {
+            format!(
+                "{}/screenshot{:04}.bmp",
+                constants::SCREENSHOT_DIRECTORY,
+                number
+            )
+        })
+        .find(|path| !Path::new(path).exists())
+        .unwrap();
+    Ok(path)
+}
+
+/// Draws a line of white text on the HUD, centered horizontally.
+fn draw_centered_text(graphics: &mut Graphics, text: &str, y: i32) {
+    // text wider than the screen starts left of it
+    let x = (constants::SCREEN_WIDTH as i32 - graphics.text_width(text) as i32) / 2;
+    graphics.draw_text(
+        text,
+        x,
+        y,
+        Color::RGB(0xff, 0xff, 0xff),
+        Depth::new(Layer::Hud, 0),
+    );
+}
+
+// FNV-1a
+fn checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100_0000_01b3)
+    })
 }
@@ -0,0 +1,212 @@
+// Renders scripted scenes to an off-screen surface and compares them with the
+// reference images in tests/golden. Running the tests with UPDATE_GOLDEN=1 set
+// writes the references from what is rendered instead:
+//
+//     UPDATE_GOLDEN=1 cargo test golden
+
+use animation::AnimationSet;
+use constants;
+use entities::Entities;
+use graphics::{with_test_graphics, Depth, Graphics, Image, Layer, Transform};
+use map::Map;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::surface::Surface;
+use std::env;
+use std::fs;
+use std::path::Path;
+use units::Milliseconds;
+
+const GOLDEN_DIRECTORY: &str = "tests/golden";
+// renders that don't match are written here to look at
+const FAILED_DIRECTORY: &str = "target/golden";
+// how much a color channel may be off before the pixel counts as different,
+// so small differences in blending between SDL versions don't fail the tests
+const CHANNEL_TOLERANCE: u8 = 8;
+// how many pixels in a thousand may be different
+const DIFFERENT_PER_MILLE: usize = 1;
+
+/// Draws `scene` on a cleared screen and reads back the result.
+fn render<F>(scene: F) -> Image
+where
+    F: for<'a> FnOnce(&mut Graphics<'a>),
+{
+    with_test_graphics(|graphics| {
+        graphics.set_color_key(Some(constants::TRANSPARENT_COLOR));
+        graphics.resize();
+        graphics.load_font(constants::FONT).unwrap();
+
+        graphics.clear();
+        scene(graphics);
+        graphics.present();
+        graphics.read_frame().unwrap()
+    })
+}
+
+fn load_bmp(path: &str) -> Result<Image, String> {
+    let loaded = Surface::load_bmp(path)?;
+    let (width, height) = loaded.size();
+    let mut surface = Surface::new(width, height, PixelFormatEnum::RGB24)?;
+    loaded.blit(None, &mut surface, None)?;
+
+    // rows of the surface can be padded
+    let pitch = surface.pitch() as usize;
+    let pixels = surface.with_lock(|data| {
+        data.chunks(pitch)
+            .take(height as usize)
+            .flat_map(|row| row[..width as usize * 3].iter().cloned())
+            .collect()
+    });
+    Ok(Image {
+        width: width,
+        height: height,
+        pixels: pixels,
+    })
+}
+
+/// Counts the pixels that differ by more than the tolerance.
+fn count_differences(actual: &Image, expected: &Image) -> usize {
+    actual
+        .pixels
+        .chunks(3)
+        .zip(expected.pixels.chunks(3))
+        .filter(|&(actual, expected)| {
+            actual
+                .iter()
+                .zip(expected)
+                .any(|(&a, &b)| (a as i32 - b as i32).abs() > CHANNEL_TOLERANCE as i32)
+        })
+        .count()
+}
+
+fn assert_matches_golden(name: &str, actual: &Image) {
+    let path = format!("{}/{}.bmp", GOLDEN_DIRECTORY, name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(GOLDEN_DIRECTORY).unwrap();
+        actual.save_bmp(&path).unwrap();
+        return;
+    }
+    if !Path::new(&path).exists() {
+        panic!(
+            "{} doesn't exist, run the tests with UPDATE_GOLDEN=1 to create it",
+            path
+        );
+    }
+
+    let expected = load_bmp(&path).unwrap();
+    let failure = if (actual.width, actual.height) != (expected.width, expected.height) {
+        Some(format!(
+            "rendered {}x{}, expected {}x{}",
+            actual.width, actual.height, expected.width, expected.height
+        ))
+    } else {
+        let different = count_differences(actual, &expected);
+        let allowed = (actual.width * actual.height) as usize * DIFFERENT_PER_MILLE / 1000;
+        if different > allowed {
+            Some(format!(
+                "{} pixels differ, at most {} may",
+                different, allowed
+            ))
+        } else {
+            None
+        }
+    };
+    if let Some(failure) = failure {
+        fs::create_dir_all(FAILED_DIRECTORY).unwrap();
+        let failed_path = format!("{}/{}.bmp", FAILED_DIRECTORY, name);
+        actual.save_bmp(&failed_path).unwrap();
+        panic!(
+            "{} doesn't match {}: {}, the render is saved to {}",
+            name, path, failure, failed_path
+        );
+    }
+}
+
+#[test]
+fn matches_within_tolerance() {
+    let image = |pixels: Vec<u8>| Image {
+        width: 2,
+        height: 1,
+        pixels: pixels,
+    };
+    let expected = image(vec![10, 20, 30, 200, 200, 200]);
+    assert_eq!(
+        count_differences(&image(vec![18, 12, 30, 200, 200, 200]), &expected),
+        0
+    );
+    assert_eq!(
+        count_differences(&image(vec![10, 20, 39, 200, 200, 200]), &expected),
+        1
+    );
+    assert_eq!(
+        count_differences(&image(vec![0, 0, 0, 0, 0, 0]), &expected),
+        2
+    );
+}
+
+/// Every player animation stepped through five frames, facing left and,
+/// mirrored, right.
+#[test]
+fn player_animations() {
+    let frame = render(|graphics| {
+        let set = AnimationSet::load(constants::PLAYER_ANIMATIONS, |name| Some(name.to_string()))
+            .unwrap();
+        let depth = Depth::new(Layer::Entities, 0);
+        for (row, (name, animation)) in set.animations.iter().enumerate() {
+            let y = 16 + row as i32 * 36;
+            let mut sprite = animation.load_sprite(graphics, &set.sheet).unwrap();
+            for step in 0..5 {
+                sprite.draw(graphics, depth, 16 + step * 48, y);
+                sprite.update(Milliseconds::new(100));
+            }
+            sprite.reset();
+            let mirrored = Transform {
+                flip_horizontal: true,
+                ..Transform::none()
+            };
+            sprite.draw_transformed(graphics, depth, 256, y, mirrored);
+            graphics.draw_text(
+                name,
+                304,
+                y + 8,
+                Color::RGB(0xff, 0xff, 0xff),
+                Depth::new(Layer::Hud, 0),
+            );
+        }
+    });
+    assert_matches_golden("player_animations", &frame);
+}
+
+/// The first frame of the game on the test map, with the player's collision
+/// boxes.
+#[test]
+fn test_map() {
+    let frame = render(|graphics| {
+        let map = Map::create_test_map(graphics).unwrap();
+        let entities = Entities::new(graphics, map).unwrap();
+        entities.draw(graphics, 1.0);
+        entities.draw_debug(graphics, 1.0);
+    });
+    assert_matches_golden("test_map", &frame);
+}
+
+/// Text in a few colors, with characters the font doesn't have.
+#[test]
+fn text() {
+    let frame = render(|graphics| {
+        let lines = [
+            "The quick brown fox jumps over the lazy dog.",
+            "0123456789 !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~",
+            "Unknown characters: \u{e9}\u{fc}\u{2603}",
+        ];
+        let colors = [
+            Color::RGB(0xff, 0xff, 0xff),
+            Color::RGB(0xff, 0xff, 0x00),
+            Color::RGB(0xff, 0x40, 0x40),
+        ];
+        for (index, (text, &color)) in lines.iter().zip(&colors).enumerate() {
+            let y = 16 + index as i32 * 24;
+            graphics.draw_text(text, 16, y, color, Depth::new(Layer::Hud, 0));
+        }
+    });
+    assert_matches_golden("text", &frame);
+}
@@ -1,29 +1,357 @@
 use constants;
+use font::Font;
 use sdl2;
-use sdl2::pixels::Color;
+use sdl2::image::LoadSurface;
+use sdl2::pixels::{Color, PixelFormatEnum};
 use sdl2::rect::Rect;
-use sdl2::render::Texture;
+use sdl2::render::{BlendMode, Canvas, RenderTarget, SurfaceCanvas, Texture, TextureCreator};
 use sdl2::surface::Surface;
+use sdl2::video::{FullscreenType, Window};
 use std::collections::BTreeMap;
 use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
 use std::rc::Rc;
 
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+const BMP_MAGIC: &[u8] = b"BM";
+
+// size of the placeholder used for missing images, it has to cover every
+// source rectangle of the sprite sheets and tilesets we use. The largest is
+// MyChar.bmp at 400x768
+const PLACEHOLDER_SIZE: u32 = 1024;
+const PLACEHOLDER_CHECK_SIZE: u32 = 16;
+
+#[derive(Debug)]
+pub enum AssetError {
+    Missing(String),
+    Decode(String, String),
+    Texture(String, String),
+    /// A definition file that doesn't make sense, the location is the path and
+    /// possibly the line.
+    Invalid(String, String),
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AssetError::Missing(ref path) => write!(f, "{}: file not found", path),
+            AssetError::Decode(ref path, ref error) => {
+                write!(f, "{}: could not decode image: {}", path, error)
+            }
+            AssetError::Texture(ref path, ref error) => {
+                write!(f, "{}: could not create texture: {}", path, error)
+            }
+            AssetError::Invalid(ref location, ref message) => {
+                write!(f, "{}: {}", location, message)
+            }
+        }
+    }
+}
+
+impl Error for AssetError {}
+
+/// Image formats `Graphics::load_image` understands, told apart by the first
+/// bytes of the file rather than its extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ImageFormat {
+    Bmp,
+    Png,
+}
+
+impl ImageFormat {
+    fn detect(header: &[u8]) -> Option<ImageFormat> {
+        if header.starts_with(PNG_MAGIC) {
+            Some(ImageFormat::Png)
+        } else if header.starts_with(BMP_MAGIC) {
+            Some(ImageFormat::Bmp)
+        } else {
+            None
+        }
+    }
+}
+
+/// A frame read back from the screen, as packed RGB24 pixels.
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn save_bmp(&self, path: &str) -> Result<(), String> {
+        // the surface borrows its pixels mutably, so it gets a copy
+        let mut pixels = self.pixels.clone();
+        let surface = Surface::from_data(
+            &mut pixels,
+            self.width,
+            self.height,
+            self.width * 3,
+            PixelFormatEnum::RGB24,
+        )?;
+        surface
+            .save_bmp(path)
+            .map_err(|error| format!("{}: {}", path, error))
+    }
+}
+
+/// How a texture is mirrored and rotated when it is drawn. The angle is in
+/// degrees clockwise around the center of the destination.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    pub angle: f64,
+}
+
+impl Transform {
+    pub fn none() -> Transform {
+        Transform {
+            flip_horizontal: false,
+            flip_vertical: false,
+            angle: 0.0,
+        }
+    }
+}
+
+/// The layers a frame is built up from, from back to front.
+// not every layer has something drawn on it yet
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Layer {
+    Background,
+    /// tiles behind entities
+    Tiles,
+    Entities,
+    /// tiles in front of entities
+    Foreground,
+    Hud,
+}
+
+/// Where a draw call ends up in the frame. Within a layer lower orders are
+/// drawn first, calls with the same order are drawn in the order they were
+/// made.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Depth {
+    pub layer: Layer,
+    pub order: i32,
+}
+
+impl Depth {
+    pub fn new(layer: Layer, order: i32) -> Depth {
+        Depth {
+            layer: layer,
+            order: order,
+        }
+    }
+}
+
+struct DrawCommand<'a> {
+    depth: Depth,
+    kind: DrawKind<'a>,
+}
+
+enum DrawKind<'a> {
+    Texture {
+        texture: Rc<Texture<'a>>,
+        source: Rect,
+        destination: Rect,
+        transform: Transform,
+    },
+    /// a character of the loaded font, tinted with the color
+    Glyph {
+        source: Rect,
+        destination: Rect,
+        color: Color,
+    },
+    Outline(Rect, Color),
+    Fill(Rect, Color),
+}
+
+/// The drawing operations `Graphics` needs from whatever it renders to. This
+/// is implemented for both window and off-screen surface canvases.
+pub trait RenderBackend {
+    fn clear(&mut self);
+    fn present(&mut self);
+    /// Copies part of a texture.
+    fn copy(
+        &mut self,
+        texture: &Texture,
+        source: Rect,
+        destination: Rect,
+        transform: Transform,
+    ) -> Result<(), String>;
+    /// Draws the outline of a rectangle.
+    fn outline(&mut self, rect: Rect, color: Color) -> Result<(), String>;
+    /// Draws a rectangle filled with a single color.
+    fn fill(&mut self, rect: Rect, color: Color) -> Result<(), String>;
+    /// Reads back the screen as it is currently drawn, at the size it is shown
+    /// at and without the black bars around it.
+    fn read_frame(&mut self) -> Result<Image, String>;
+    /// Scales the screen by the largest whole factor that fits the output and
+    /// centers it, leaving black bars around it.
+    fn fit_to_output(&mut self) -> Result<(), String>;
+    fn set_fullscreen(&mut self, fullscreen: bool) -> Result<(), String>;
+}
+
+/// What differs between rendering to a window and to a surface.
+pub trait OutputTarget: RenderTarget + Sized {
+    fn set_fullscreen(canvas: &mut Canvas<Self>, fullscreen: bool) -> Result<(), String>;
+}
+
+impl OutputTarget for Window {
+    fn set_fullscreen(canvas: &mut Canvas<Window>, fullscreen: bool) -> Result<(), String> {
+        canvas.window_mut().set_fullscreen(if fullscreen {
+            FullscreenType::Desktop
+        } else {
+            FullscreenType::Off
+        })
+    }
+}
+
+impl<'s> OutputTarget for Surface<'s> {
+    fn set_fullscreen(_: &mut Canvas<Surface<'s>>, _: bool) -> Result<(), String> {
+        Err("off-screen surfaces can't be fullscreen".to_string())
+    }
+}
+
+impl<T: OutputTarget> RenderBackend for Canvas<T> {
+    fn clear(&mut self) {
+        Canvas::clear(self);
+    }
+
+    fn present(&mut self) {
+        Canvas::present(self);
+    }
+
+    fn copy(
+        &mut self,
+        texture: &Texture,
+        source: Rect,
+        destination: Rect,
+        transform: Transform,
+    ) -> Result<(), String> {
+        if transform == Transform::none() {
+            Canvas::copy(self, texture, source, destination)
+        } else {
+            Canvas::copy_ex(
+                self,
+                texture,
+                source,
+                destination,
+                transform.angle,
+                None,
+                transform.flip_horizontal,
+                transform.flip_vertical,
+            )
+        }
+    }
+
+    fn outline(&mut self, rect: Rect, color: Color) -> Result<(), String> {
+        // the draw color is also what the screen is cleared to
+        let clear_color = self.draw_color();
+        self.set_draw_color(color);
+        let result = self.draw_rect(rect);
+        self.set_draw_color(clear_color);
+        result
+    }
+
+    fn fill(&mut self, rect: Rect, color: Color) -> Result<(), String> {
+        // like for outlines, the clear color has to be put back afterwards
+        let clear_color = self.draw_color();
+        self.set_draw_color(color);
+        let result = self.fill_rect(rect);
+        self.set_draw_color(clear_color);
+        result
+    }
+
+    fn read_frame(&mut self) -> Result<Image, String> {
+        // the viewport is in scaled coordinates, the area read is given in
+        // output pixels with scaling and the viewport turned off
+        let (scale_x, scale_y) = self.scale();
+        let viewport = self.viewport();
+        let area = Rect::new(
+            (viewport.x() as f32 * scale_x) as i32,
+            (viewport.y() as f32 * scale_y) as i32,
+            (viewport.width() as f32 * scale_x) as u32,
+            (viewport.height() as f32 * scale_y) as u32,
+        );
+        self.set_scale(1.0, 1.0)?;
+        self.set_viewport(None);
+        let pixels = Canvas::read_pixels(self, area, PixelFormatEnum::RGB24);
+        self.set_scale(scale_x, scale_y)?;
+        self.set_viewport(viewport);
+        Ok(Image {
+            width: area.width(),
+            height: area.height(),
+            pixels: pixels?,
+        })
+    }
+
+    fn fit_to_output(&mut self) -> Result<(), String> {
+        let (width, height) = self.output_size()?;
+        let scale = (width / constants::SCREEN_WIDTH)
+            .min(height / constants::SCREEN_HEIGHT)
+            .max(1);
+        self.set_scale(scale as f32, scale as f32)?;
+
+        // the viewport is given in scaled coordinates
+        let x = width.saturating_sub(constants::SCREEN_WIDTH * scale) / 2 / scale;
+        let y = height.saturating_sub(constants::SCREEN_HEIGHT * scale) / 2 / scale;
+        self.set_viewport(Rect::new(
+            x as i32,
+            y as i32,
+            constants::SCREEN_WIDTH,
+            constants::SCREEN_HEIGHT,
+        ));
+        Ok(())
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool) -> Result<(), String> {
+        T::set_fullscreen(self, fullscreen)
+    }
+}
+
+pub trait TextureLoader {
+    fn create_texture(&self, surface: Surface) -> Result<Texture<'_>, String>;
+}
+
+impl<T> TextureLoader for TextureCreator<T> {
+    fn create_texture(&self, surface: Surface) -> Result<Texture<'_>, String> {
+        self.create_texture_from_surface(surface)
+            .map_err(|e| e.description().to_owned())
+    }
+}
+
 pub struct Graphics<'a> {
-    canvas: &'a mut sdl2::render::WindowCanvas,
-    texture_creator: &'a sdl2::render::TextureCreator<sdl2::video::WindowContext>,
+    canvas: &'a mut (RenderBackend + 'a),
+    texture_creator: &'a (TextureLoader + 'a),
     texture_cache: BTreeMap<String, Rc<sdl2::render::Texture<'a>>>,
+    color_key: Option<Color>,
+    missing_texture_fallback: bool,
+    fullscreen: bool,
+    draw_queue: Vec<DrawCommand<'a>>,
+    font: Option<Font<'a>>,
+    screenshots: Vec<String>,
 }
 
 impl<'a> Graphics<'a> {
-    pub fn load_canvas(context: &sdl2::Sdl) -> Result<sdl2::render::WindowCanvas, String> {
+    /// Creates a resizable window, initially `scale` times the size of the
+    /// screen.
+    pub fn load_canvas(
+        context: &sdl2::Sdl,
+        scale: u32,
+    ) -> Result<sdl2::render::WindowCanvas, String> {
         let window = context
             .video()?
             .window(
                 "Cave Story",
-                constants::SCREEN_WIDTH,
-                constants::SCREEN_HEIGHT,
+                constants::SCREEN_WIDTH * scale,
+                constants::SCREEN_HEIGHT * scale,
             )
-            //.fullscreen()
+            .resizable()
             .build()
             .map_err(|e| e.description().to_owned())?;
         let mut canvas = window
@@ -39,35 +367,212 @@ impl<'a> Graphics<'a> {
         Ok(canvas)
     }
 
+    /// Creates an off-screen canvas the size of the screen, for running without
+    /// a window.
+    pub fn load_software_canvas() -> Result<SurfaceCanvas<'static>, String> {
+        let surface = Surface::new(
+            constants::SCREEN_WIDTH,
+            constants::SCREEN_HEIGHT,
+            PixelFormatEnum::RGB888,
+        )?;
+        let mut canvas = surface.into_canvas()?;
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+        canvas.clear();
+
+        Ok(canvas)
+    }
+
     pub fn new(
-        canvas: &'a mut sdl2::render::WindowCanvas,
-        texture_creator: &'a sdl2::render::TextureCreator<sdl2::video::WindowContext>,
+        canvas: &'a mut (RenderBackend + 'a),
+        texture_creator: &'a (TextureLoader + 'a),
     ) -> Graphics<'a> {
         Graphics {
             canvas: canvas,
             texture_creator: texture_creator,
             texture_cache: BTreeMap::new(),
+            color_key: None,
+            missing_texture_fallback: false,
+            fullscreen: false,
+            draw_queue: Vec::new(),
+            font: None,
+            screenshots: Vec::new(),
         }
     }
 
-    pub fn load_image(&mut self, filename: &str) -> Rc<Texture<'a>> {
-        let texture_creator = &self.texture_creator;
-        Rc::clone(
-            self.texture_cache
-                .entry(filename.to_string())
-                .or_insert_with(|| {
-                    let surface = Surface::load_bmp(filename).expect("Failed to load bitmap");
-                    let texture = texture_creator
-                        .create_texture_from_surface(surface)
-                        .expect("Failed to create texture from surface");
-                    Rc::new(texture)
-                }),
-        )
+    /// Sets the color that is drawn as transparent in images loaded from now
+    /// on. Images that are already cached keep the color key they were loaded
+    /// with.
+    pub fn set_color_key(&mut self, color_key: Option<Color>) {
+        self.color_key = color_key;
     }
 
-    pub fn blit_surface(&mut self, texture: &Texture, source: Rect, destination: Rect) {
-        if let Err(error) = self.canvas.copy(texture, source, destination) {
-            println!("error copying texture: {}", error);
+    /// When enabled, images that fail to load are replaced by a checkerboard
+    /// placeholder instead of returning an error. Meant for development, so
+    /// missing art doesn't stop the game from starting.
+    pub fn set_missing_texture_fallback(&mut self, enabled: bool) {
+        self.missing_texture_fallback = enabled;
+    }
+
+    pub fn load_image(&mut self, filename: &str) -> Result<Rc<Texture<'a>>, AssetError> {
+        if let Some(texture) = self.texture_cache.get(filename) {
+            return Ok(Rc::clone(texture));
+        }
+
+        let texture = Rc::new(self.load_texture(filename)?);
+        self.texture_cache
+            .insert(filename.to_string(), Rc::clone(&texture));
+        Ok(texture)
+    }
+
+    /// Loads an image into a texture of its own, outside the cache, for
+    /// textures that are changed when drawing them.
+    pub fn load_texture(&mut self, filename: &str) -> Result<Texture<'a>, AssetError> {
+        match self.create_texture(filename) {
+            Ok(texture) => Ok(texture),
+            Err(error) => {
+                if !self.missing_texture_fallback {
+                    return Err(error);
+                }
+                warn!("{}, using a placeholder", error);
+                self.create_placeholder(filename)
+            }
+        }
+    }
+
+    fn create_texture(&self, filename: &str) -> Result<Texture<'a>, AssetError> {
+        if !Path::new(filename).exists() {
+            return Err(AssetError::Missing(filename.to_string()));
+        }
+        let decode_error = |error| AssetError::Decode(filename.to_string(), error);
+
+        let mut header = Vec::new();
+        File::open(filename)
+            .and_then(|file| file.take(PNG_MAGIC.len() as u64).read_to_end(&mut header))
+            .map_err(|error| decode_error(error.to_string()))?;
+
+        match ImageFormat::detect(&header) {
+            Some(ImageFormat::Bmp) => {
+                // BMPs have no alpha channel, they rely on the color key instead
+                let mut surface = Surface::load_bmp(filename).map_err(&decode_error)?;
+                if let Some(color) = self.color_key {
+                    surface.set_color_key(true, color).map_err(&decode_error)?;
+                }
+                self.texture_creator
+                    .create_texture(surface)
+                    .map_err(|error| AssetError::Texture(filename.to_string(), error))
+            }
+            Some(ImageFormat::Png) => {
+                let surface = Surface::from_file(filename).map_err(&decode_error)?;
+                let mut texture = self
+                    .texture_creator
+                    .create_texture(surface)
+                    .map_err(|error| AssetError::Texture(filename.to_string(), error))?;
+                texture.set_blend_mode(BlendMode::Blend);
+                Ok(texture)
+            }
+            None => Err(decode_error("unsupported image format".to_string())),
+        }
+    }
+
+    fn create_placeholder(&self, filename: &str) -> Result<Texture<'a>, AssetError> {
+        let placeholder_error = |error| AssetError::Texture(filename.to_string(), error);
+        let mut surface = Surface::new(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE, PixelFormatEnum::RGB888)
+            .map_err(&placeholder_error)?;
+        surface
+            .fill_rect(None, Color::RGB(0, 0, 0))
+            .map_err(&placeholder_error)?;
+        let checks = PLACEHOLDER_SIZE / PLACEHOLDER_CHECK_SIZE;
+        for row in 0..checks {
+            for col in (row % 2..checks).step_by(2) {
+                let check = Rect::new(
+                    (col * PLACEHOLDER_CHECK_SIZE) as i32,
+                    (row * PLACEHOLDER_CHECK_SIZE) as i32,
+                    PLACEHOLDER_CHECK_SIZE,
+                    PLACEHOLDER_CHECK_SIZE,
+                );
+                surface
+                    .fill_rect(check, Color::RGB(0xff, 0, 0xff))
+                    .map_err(&placeholder_error)?;
+            }
+        }
+        self.texture_creator
+            .create_texture(surface)
+            .map_err(placeholder_error)
+    }
+
+    /// Queues a texture to be drawn when the frame is presented.
+    pub fn blit_surface(
+        &mut self,
+        texture: Rc<Texture<'a>>,
+        source: Rect,
+        destination: Rect,
+        transform: Transform,
+        depth: Depth,
+    ) {
+        self.draw_queue.push(DrawCommand {
+            depth: depth,
+            kind: DrawKind::Texture {
+                texture: texture,
+                source: source,
+                destination: destination,
+                transform: transform,
+            },
+        });
+    }
+
+    /// Queues the outline of a rectangle, for debugging.
+    pub fn draw_rect(&mut self, rect: Rect, color: Color, depth: Depth) {
+        self.draw_queue.push(DrawCommand {
+            depth: depth,
+            kind: DrawKind::Outline(rect, color),
+        });
+    }
+
+    /// Queues a rectangle filled with a single color.
+    pub fn fill_rect(&mut self, rect: Rect, color: Color, depth: Depth) {
+        self.draw_queue.push(DrawCommand {
+            depth: depth,
+            kind: DrawKind::Fill(rect, color),
+        });
+    }
+
+    /// Loads the font `draw_text` uses.
+    pub fn load_font(&mut self, path: &str) -> Result<(), AssetError> {
+        let font = Font::load(self, path)?;
+        self.font = Some(font);
+        Ok(())
+    }
+
+    /// Width in pixels of the longest line of `text` in the loaded font.
+    pub fn text_width(&self, text: &str) -> u32 {
+        self.font
+            .as_ref()
+            .map_or(0, |font| font.metrics.text_width(text))
+    }
+
+    /// Queues `text` to be drawn in `color` with its top left at `x`, `y`. Lines
+    /// are separated by newlines. Nothing is drawn before a font is loaded.
+    pub fn draw_text(&mut self, text: &str, x: i32, y: i32, color: Color, depth: Depth) {
+        let font = match self.font {
+            Some(ref font) => font,
+            None => return,
+        };
+        for (line_number, line) in text.lines().enumerate() {
+            let line_y = y + (line_number as u32 * font.metrics.cell_height) as i32;
+            let mut glyph_x = x;
+            for character in line.chars() {
+                if let Some(glyph) = font.metrics.glyph(font.metrics.fallback(character)) {
+                    self.draw_queue.push(DrawCommand {
+                        depth: depth,
+                        kind: DrawKind::Glyph {
+                            source: glyph,
+                            destination: Rect::new(glyph_x, line_y, glyph.width(), glyph.height()),
+                            color: color,
+                        },
+                    });
+                    glyph_x += glyph.width() as i32;
+                }
+            }
         }
     }
 
@@ -75,7 +580,160 @@ impl<'a> Graphics<'a> {
         self.canvas.clear();
     }
 
+    /// Draws everything queued since the last frame, back to front, and shows
+    /// the result.
     pub fn present(&mut self) {
+        // the sort is stable, so draw calls at the same depth keep their order
+        self.draw_queue.sort_by_key(|command| command.depth);
+        for command in self.draw_queue.drain(..) {
+            let result = match command.kind {
+                DrawKind::Texture {
+                    texture,
+                    source,
+                    destination,
+                    transform,
+                } => self.canvas.copy(&texture, source, destination, transform),
+                DrawKind::Glyph {
+                    source,
+                    destination,
+                    color,
+                } => match self.font {
+                    // only the font uses its texture, so the tint can stay set
+                    // until the next glyph
+                    Some(ref mut font) => {
+                        font.sheet.set_color_mod(color.r, color.g, color.b);
+                        self.canvas
+                            .copy(&font.sheet, source, destination, Transform::none())
+                    }
+                    None => Ok(()),
+                },
+                DrawKind::Outline(rect, color) => self.canvas.outline(rect, color),
+                DrawKind::Fill(rect, color) => self.canvas.fill(rect, color),
+            };
+            if let Err(error) = result {
+                error!("Could not draw: {}", error);
+            }
+        }
+        // windows don't keep what was presented, so the frame is read back
+        // before it is shown
+        for path in self.screenshots.drain(..) {
+            match self
+                .canvas
+                .read_frame()
+                .and_then(|frame| frame.save_bmp(&path))
+            {
+                Ok(()) => debug!("Saved frame to {}", path),
+                Err(error) => error!("Could not save screenshot: {}", error),
+            }
+        }
         self.canvas.present();
     }
+
+    /// Fits the screen to the window again after it changed size.
+    pub fn resize(&mut self) {
+        if let Err(error) = self.canvas.fit_to_output() {
+            warn!("Could not scale to the window size: {}", error);
+        }
+    }
+
+    pub fn toggle_fullscreen(&mut self) {
+        let fullscreen = !self.fullscreen;
+        match self.canvas.set_fullscreen(fullscreen) {
+            Ok(()) => {
+                self.fullscreen = fullscreen;
+                self.resize();
+            }
+            Err(error) => warn!("Could not toggle fullscreen: {}", error),
+        }
+    }
+
+    /// Saves the next frame that is presented to a BMP file.
+    pub fn save_screenshot(&mut self, path: &str) {
+        self.screenshots.push(path.to_string());
+    }
+
+    /// Reads back the frame that was presented last. Only off-screen canvases
+    /// keep it after presenting, windows need `save_screenshot`.
+    pub fn read_frame(&mut self) -> Result<Image, String> {
+        self.canvas.read_frame()
+    }
+}
+
+/// Runs `test` with graphics that draw to an off-screen canvas, for tests of
+/// anything that loads textures.
+#[cfg(test)]
+pub fn with_test_graphics<F, R>(test: F) -> R
+where
+    F: for<'a> FnOnce(&mut Graphics<'a>) -> R,
+{
+    let mut canvas = Graphics::load_software_canvas().unwrap();
+    let texture_creator = canvas.texture_creator();
+    let mut graphics = Graphics::new(&mut canvas, &texture_creator);
+    test(&mut graphics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn detects_image_format() {
+        assert_eq!(
+            ImageFormat::detect(b"\x89PNG\r\n\x1a\n\x00\x00"),
+            Some(ImageFormat::Png)
+        );
+        assert_eq!(ImageFormat::detect(b"BM6\x10\x00"), Some(ImageFormat::Bmp));
+        assert_eq!(ImageFormat::detect(b"GIF89a"), None);
+        assert_eq!(ImageFormat::detect(b""), None);
+    }
+
+    #[test]
+    fn loads_png_with_transparency() {
+        // the left column is opaque red, the rest is transparent green
+        with_test_graphics(|graphics| {
+            let texture = graphics
+                .load_image("tests/fixtures/Transparent.png")
+                .unwrap();
+            let query = texture.query();
+            assert_eq!((query.width, query.height), (3, 2));
+
+            let white = Color::RGB(255, 255, 255);
+            let area = Rect::new(0, 0, 3, 2);
+            graphics.clear();
+            graphics.fill_rect(area, white, Depth::new(Layer::Background, 0));
+            graphics.blit_surface(
+                texture,
+                area,
+                area,
+                Transform::none(),
+                Depth::new(Layer::Entities, 0),
+            );
+            graphics.present();
+            let frame = graphics.read_frame().unwrap();
+            let pixel = |x: usize, y: usize| {
+                let index = (y * frame.width as usize + x) * 3;
+                &frame.pixels[index..index + 3]
+            };
+            assert_eq!(pixel(0, 1), &[255, 0, 0]);
+            assert_eq!(pixel(2, 1), &[255, 255, 255]);
+        });
+    }
+
+    #[test]
+    fn placeholder_covers_every_image() {
+        for entry in fs::read_dir("content").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|extension| extension.to_str()) == Some("bmp") {
+                let (width, height) = Surface::load_bmp(&path).unwrap().size();
+                assert!(
+                    width <= PLACEHOLDER_SIZE && height <= PLACEHOLDER_SIZE,
+                    "{} is {}x{}",
+                    path.display(),
+                    width,
+                    height
+                );
+            }
+        }
+    }
 }
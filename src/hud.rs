@@ -0,0 +1,84 @@
+use constants;
+use graphics::{Depth, Graphics, Layer};
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use units::Milliseconds;
+use weapon::{Experience, LevelChange};
+
+// experience bar, in the bottom left corner of the screen
+const BAR_X: i32 = 64;
+const BAR_WIDTH: u32 = 80;
+const BAR_HEIGHT: u32 = 12;
+const MARGIN: i32 = 16;
+
+/// The weapon's level and experience, and a message for a while after the
+/// level changed.
+pub struct Hud {
+    level_message: Option<(LevelChange, Milliseconds)>,
+}
+
+impl Hud {
+    pub fn new() -> Hud {
+        Hud {
+            level_message: None,
+        }
+    }
+
+    pub fn level_changed(&mut self, change: LevelChange) {
+        self.level_message = Some((change, Milliseconds::new(constants::LEVEL_MESSAGE_TIME)));
+    }
+
+    pub fn update(&mut self, elapsed_time: Milliseconds) {
+        self.level_message = match self.level_message {
+            Some((change, time)) if time > elapsed_time => Some((change, time - elapsed_time)),
+            _ => None,
+        };
+    }
+
+    pub fn draw(&self, graphics: &mut Graphics, experience: &Experience) {
+        let white = Color::RGB(0xff, 0xff, 0xff);
+        let y = constants::SCREEN_HEIGHT as i32 - MARGIN - BAR_HEIGHT as i32;
+        graphics.draw_text(
+            &format!("Lv{}", experience.level()),
+            MARGIN,
+            y - 2,
+            white,
+            Depth::new(Layer::Hud, 0),
+        );
+
+        let filled = if experience.is_max() {
+            BAR_WIDTH
+        } else {
+            BAR_WIDTH * experience.points() / experience.threshold()
+        };
+        if filled > 0 {
+            graphics.fill_rect(
+                Rect::new(BAR_X + 1, y + 1, filled, BAR_HEIGHT),
+                Color::RGB(0xff, 0xc0, 0x20),
+                Depth::new(Layer::Hud, 0),
+            );
+        }
+        graphics.draw_rect(
+            Rect::new(BAR_X, y, BAR_WIDTH + 2, BAR_HEIGHT + 2),
+            white,
+            Depth::new(Layer::Hud, 0),
+        );
+        if experience.is_max() {
+            graphics.draw_text(
+                "MAX",
+                BAR_X + BAR_WIDTH as i32 + 8,
+                y - 2,
+                white,
+                Depth::new(Layer::Hud, 0),
+            );
+        }
+
+        if let Some((change, _)) = self.level_message {
+            let (text, color) = match change {
+                LevelChange::Up(_) => ("Level up!", Color::RGB(0xff, 0xff, 0x40)),
+                LevelChange::Down(_) => ("Level down!", Color::RGB(0xff, 0x40, 0x40)),
+            };
+            graphics.draw_text(text, MARGIN, y - 24, color, Depth::new(Layer::Hud, 0));
+        }
+    }
+}
@@ -1,6 +1,39 @@
+use bindings::{Action, Bindings};
+use constants;
+use sdl2::controller::{Axis, Button};
 use sdl2::keyboard::Keycode;
 use std::collections::BTreeMap;
 
+/// A change in input state, as delivered by the event pump or a replay.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent {
+    KeyDown(Keycode),
+    KeyUp(Keycode),
+    ButtonDown(Button),
+    ButtonUp(Button),
+    AxisMotion(Axis, i16),
+    /// A controller was unplugged, everything it was holding is let go.
+    ControllerRemoved,
+}
+
+/// Anything that can be held down. The left analog stick counts as four
+/// directions that are held while it is pushed past the dead zone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Control {
+    Key(i32),
+    Button(i32),
+    StickLeft,
+    StickRight,
+    StickUp,
+    StickDown,
+}
+
+impl Control {
+    fn is_controller(self) -> bool {
+        !matches!(self, Control::Key(_))
+    }
+}
+
 struct KeyState {
     held: bool,
     pressed: bool,
@@ -18,13 +51,15 @@ impl KeyState {
 }
 
 pub struct Input {
-    input_keys: BTreeMap<i32, KeyState>,
+    input_keys: BTreeMap<Control, KeyState>,
+    bindings: Bindings,
 }
 
 impl Input {
-    pub fn new() -> Input {
+    pub fn new(bindings: Bindings) -> Input {
         Input {
             input_keys: BTreeMap::new(),
+            bindings: bindings,
         }
     }
     pub fn begin_new_frame(&mut self) {
@@ -34,35 +69,168 @@ impl Input {
         }
     }
 
-    pub fn on_key_down(&mut self, key: Keycode) {
-        let state = self.input_keys.entry(key as i32).or_insert(KeyState::new());
+    pub fn handle_event(&mut self, event: InputEvent) {
+        match event {
+            InputEvent::KeyDown(key) => self.on_down(Control::Key(key as i32)),
+            InputEvent::KeyUp(key) => self.on_up(Control::Key(key as i32)),
+            InputEvent::ButtonDown(button) => self.on_down(Control::Button(button as i32)),
+            InputEvent::ButtonUp(button) => self.on_up(Control::Button(button as i32)),
+            InputEvent::AxisMotion(Axis::LeftX, value) => {
+                self.on_axis(Control::StickLeft, Control::StickRight, value)
+            }
+            InputEvent::AxisMotion(Axis::LeftY, value) => {
+                self.on_axis(Control::StickUp, Control::StickDown, value)
+            }
+            InputEvent::AxisMotion(..) => (),
+            InputEvent::ControllerRemoved => {
+                let held: Vec<Control> = self
+                    .input_keys
+                    .iter()
+                    .filter(|&(control, state)| control.is_controller() && state.held)
+                    .map(|(&control, _)| control)
+                    .collect();
+                for control in held {
+                    self.on_up(control);
+                }
+            }
+        }
+    }
+
+    fn on_axis(&mut self, negative: Control, positive: Control, value: i16) {
+        let (negative_held, positive_held) = if value < -constants::CONTROLLER_DEAD_ZONE {
+            (true, false)
+        } else if value > constants::CONTROLLER_DEAD_ZONE {
+            (false, true)
+        } else {
+            (false, false)
+        };
+
+        // the stick sends a stream of motion events, only the ones crossing the
+        // dead zone change anything
+        for &(control, held) in &[(negative, negative_held), (positive, positive_held)] {
+            if held && !self.held(control) {
+                self.on_down(control);
+            } else if !held && self.held(control) {
+                self.on_up(control);
+            }
+        }
+    }
+
+    fn on_down(&mut self, control: Control) {
+        let state = self.input_keys.entry(control).or_insert(KeyState::new());
         if !state.held {
             state.pressed = true;
             state.held = true;
         }
     }
 
-    pub fn on_key_up(&mut self, key: Keycode) {
-        let state = self.input_keys.entry(key as i32).or_insert(KeyState::new());
+    fn on_up(&mut self, control: Control) {
+        let state = self.input_keys.entry(control).or_insert(KeyState::new());
         state.released = true;
         state.held = false;
     }
 
-    pub fn key_held(&self, key: Keycode) -> bool {
+    pub fn held(&self, control: Control) -> bool {
         self.input_keys
-            .get(&(key as i32))
+            .get(&control)
             .map_or(false, |state| state.held)
     }
 
-    pub fn key_pressed(&self, key: Keycode) -> bool {
+    pub fn pressed(&self, control: Control) -> bool {
         self.input_keys
-            .get(&(key as i32))
+            .get(&control)
             .map_or(false, |state| state.pressed)
     }
 
-    pub fn key_released(&self, key: Keycode) -> bool {
+    pub fn released(&self, control: Control) -> bool {
         self.input_keys
-            .get(&(key as i32))
+            .get(&control)
             .map_or(false, |state| state.released)
     }
+
+    pub fn action_held(&self, action: Action) -> bool {
+        self.bindings
+            .controls(action)
+            .iter()
+            .any(|&control| self.held(control))
+    }
+
+    pub fn action_pressed(&self, action: Action) -> bool {
+        self.bindings
+            .controls(action)
+            .iter()
+            .any(|&control| self.pressed(control))
+    }
+
+    pub fn action_released(&self, action: Action) -> bool {
+        self.bindings
+            .controls(action)
+            .iter()
+            .any(|&control| self.released(control))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAST_DEAD_ZONE: i16 = constants::CONTROLLER_DEAD_ZONE + 1;
+
+    #[test]
+    fn stick_crosses_dead_zone_in_both_directions() {
+        let mut input = Input::new(Bindings::default());
+        input.handle_event(InputEvent::AxisMotion(Axis::LeftX, -PAST_DEAD_ZONE));
+        assert!(input.pressed(Control::StickLeft));
+        assert!(input.action_held(Action::MoveLeft));
+        assert!(!input.held(Control::StickRight));
+
+        // further motion on the same side doesn't press it again
+        input.begin_new_frame();
+        input.handle_event(InputEvent::AxisMotion(Axis::LeftX, i16::MIN));
+        assert!(input.held(Control::StickLeft));
+        assert!(!input.pressed(Control::StickLeft));
+
+        input.begin_new_frame();
+        input.handle_event(InputEvent::AxisMotion(
+            Axis::LeftX,
+            -constants::CONTROLLER_DEAD_ZONE,
+        ));
+        assert!(input.released(Control::StickLeft));
+        assert!(!input.held(Control::StickLeft));
+
+        input.begin_new_frame();
+        input.handle_event(InputEvent::AxisMotion(Axis::LeftX, PAST_DEAD_ZONE));
+        assert!(input.pressed(Control::StickRight));
+        assert!(input.action_held(Action::MoveRight));
+        assert!(!input.held(Control::StickLeft));
+
+        // swinging straight across releases one side and presses the other
+        input.begin_new_frame();
+        input.handle_event(InputEvent::AxisMotion(Axis::LeftX, -PAST_DEAD_ZONE));
+        assert!(input.released(Control::StickRight));
+        assert!(input.pressed(Control::StickLeft));
+
+        input.begin_new_frame();
+        input.handle_event(InputEvent::AxisMotion(Axis::LeftY, PAST_DEAD_ZONE));
+        assert!(input.action_held(Action::LookDown));
+        assert!(input.held(Control::StickLeft));
+    }
+
+    #[test]
+    fn releases_controller_when_unplugged() {
+        let mut input = Input::new(Bindings::default());
+        input.handle_event(InputEvent::ButtonDown(Button::A));
+        input.handle_event(InputEvent::AxisMotion(Axis::LeftY, -PAST_DEAD_ZONE));
+        input.handle_event(InputEvent::KeyDown(Keycode::X));
+
+        input.begin_new_frame();
+        input.handle_event(InputEvent::ControllerRemoved);
+        assert!(input.released(Control::Button(Button::A as i32)));
+        assert!(!input.action_held(Action::Jump));
+        assert!(input.released(Control::StickUp));
+        assert!(!input.action_held(Action::LookUp));
+        // the keyboard isn't part of the controller
+        assert!(input.action_held(Action::Fire));
+        assert!(!input.released(Control::Key(Keycode::X as i32)));
+    }
 }
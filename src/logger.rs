@@ -0,0 +1,164 @@
+use log::{self, LevelFilter, Log, Metadata, Record};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+const CRATE_PREFIX: &str = "cavestory::";
+
+/// Which messages are logged: a default level and levels for single modules.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogFilter {
+    default: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    /// Parses a comma separated list of levels, `module=level` sets the level
+    /// for one module and everything in it, a plain level the level for all
+    /// other modules. Modules are named without the crate, e.g.
+    /// `warn,graphics=debug`.
+    pub fn parse(spec: &str) -> Result<LogFilter, String> {
+        let mut filter = LogFilter {
+            default: LevelFilter::Info,
+            modules: Vec::new(),
+        };
+        for part in spec.split(',').map(|part| part.trim()) {
+            if part.is_empty() {
+                continue;
+            }
+            let mut pieces = part.splitn(2, '=');
+            let first = pieces.next().unwrap_or("").trim();
+            match pieces.next() {
+                Some(level) => filter
+                    .modules
+                    .push((first.to_string(), parse_level(level.trim())?)),
+                None => filter.default = parse_level(first)?,
+            }
+        }
+        Ok(filter)
+    }
+
+    /// The level for a log target, the most specific module setting wins.
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        let target = target.strip_prefix(CRATE_PREFIX).unwrap_or(target);
+        self.modules
+            .iter()
+            .filter(|(module, _)| target == module || target.starts_with(&format!("{}::", module)))
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.default, |&(_, level)| level)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|&(_, level)| level)
+            .fold(self.default, |max, level| max.max(level))
+    }
+}
+
+fn parse_level(name: &str) -> Result<LevelFilter, String> {
+    LevelFilter::from_str(name).map_err(|_| format!("unknown log level {}", name))
+}
+
+struct Logger {
+    filter: LogFilter,
+    file: Option<Mutex<File>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let target = record.target();
+        let line = format!(
+            "[{} {}] {}",
+            record.level(),
+            target.strip_prefix(CRATE_PREFIX).unwrap_or(target),
+            record.args()
+        );
+        // stderr, so the log doesn't get mixed into output of headless runs
+        eprintln!("{}", line);
+        if let Some(ref file) = self.file {
+            if let Ok(mut file) = file.lock() {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(ref file) = self.file {
+            if let Ok(mut file) = file.lock() {
+                let _ = file.flush();
+            }
+        }
+    }
+}
+
+/// Installs the logger. `spec` is parsed by `LogFilter::parse`, messages are
+/// also appended to `file` if given.
+pub fn init(spec: &str, file: Option<&str>) -> Result<(), String> {
+    let filter = LogFilter::parse(spec)?;
+    let file = match file {
+        Some(path) => Some(Mutex::new(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|error| format!("{}: {}", path, error))?,
+        )),
+        None => None,
+    };
+    log::set_max_level(filter.max_level());
+    log::set_boxed_logger(Box::new(Logger {
+        filter: filter,
+        file: file,
+    }))
+    .map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_filter() {
+        let filter = LogFilter::parse("warn, graphics=debug,game=off").unwrap();
+        assert_eq!(filter.default, LevelFilter::Warn);
+        assert_eq!(
+            filter.modules,
+            vec![
+                ("graphics".to_string(), LevelFilter::Debug),
+                ("game".to_string(), LevelFilter::Off),
+            ]
+        );
+        assert_eq!(filter.max_level(), LevelFilter::Debug);
+        assert_eq!(LogFilter::parse("").unwrap().default, LevelFilter::Info);
+    }
+
+    #[test]
+    fn rejects_unknown_levels() {
+        assert_eq!(
+            LogFilter::parse("graphics=loud"),
+            Err("unknown log level loud".to_string())
+        );
+    }
+
+    #[test]
+    fn most_specific_module_wins() {
+        let filter = LogFilter::parse("error,map=info,map::tiles=trace").unwrap();
+        assert_eq!(filter.level_for("cavestory::game"), LevelFilter::Error);
+        assert_eq!(filter.level_for("cavestory::map"), LevelFilter::Info);
+        assert_eq!(
+            filter.level_for("cavestory::map::tiles"),
+            LevelFilter::Trace
+        );
+        assert_eq!(filter.level_for("cavestory::mapping"), LevelFilter::Error);
+        assert_eq!(filter.level_for("sdl2"), LevelFilter::Error);
+    }
+}
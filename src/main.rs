@@ -1,20 +1,66 @@
+#[macro_use]
+extern crate log;
 extern crate sdl2;
 
+mod animation;
+mod bindings;
+mod camera;
 mod constants;
+mod crystal;
 mod game;
 use game::Game;
+use options::Options;
+use std::env;
+mod debug;
+mod definitions;
 mod entities;
+mod font;
+#[cfg(test)]
+mod golden;
 mod graphics;
+mod hud;
 mod input;
+mod logger;
+mod map;
+mod options;
 mod player;
+mod projectile;
+mod rectangle;
+mod replay;
 mod sprite;
+mod stage;
+mod timestep;
 mod units;
+mod weapon;
 
 fn main() {
-    match Game::new() {
+    let options = match Options::from_args() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("Invalid arguments: {}", error);
+            return;
+        }
+    };
+
+    let filter = options
+        .log
+        .clone()
+        .or_else(|| env::var(constants::LOG_VARIABLE).ok())
+        .unwrap_or_default();
+    let log_file = if options.log_file {
+        Some(constants::LOG_FILE)
+    } else {
+        None
+    };
+    if let Err(error) = logger::init(&filter, log_file) {
+        eprintln!("Could not set up logging: {}", error);
+        return;
+    }
+
+    match Game::new(options) {
         Ok(mut game) => game.event_loop(),
         Err(error) => {
-            println!("Could not initialize game: {}", error);
+            error!("Could not initialize game: {}", error);
         }
     }
 }
@@ -0,0 +1,286 @@
+use camera::Camera;
+use constants;
+use graphics::{AssetError, Depth, Graphics, Layer};
+use rectangle::Rectangle;
+use sprite::{Drawable, Sprite};
+use stage::Stage;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+// tilesets are laid out 16 tiles wide
+const TILESET_COLUMNS: i32 = 16;
+// entity type of experience crystals in .pxe files
+const CRYSTAL_ENTITY: u16 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileType {
+    Empty,
+    Solid,
+    Backdrop,
+    /// hurts the player on touch, e.g. spikes
+    Hazard,
+}
+
+#[derive(Clone)]
+struct Tile<'a> {
+    tile_type: TileType,
+    layer: Layer,
+    sprite: Option<Rc<Sprite<'a>>>,
+}
+
+impl<'a> Tile<'a> {
+    fn empty() -> Tile<'a> {
+        Tile {
+            tile_type: TileType::Empty,
+            layer: Layer::Tiles,
+            sprite: None,
+        }
+    }
+
+    fn new(tile_type: TileType, layer: Layer, sprite: Rc<Sprite<'a>>) -> Tile<'a> {
+        Tile {
+            tile_type: tile_type,
+            layer: layer,
+            sprite: Some(sprite),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CollisionTile {
+    pub row: i32,
+    pub col: i32,
+    pub tile_type: TileType,
+}
+
+pub struct Map<'a> {
+    tiles: Vec<Vec<Tile<'a>>>,
+    // centers of experience crystals in world pixels
+    crystals: Vec<(i32, i32)>,
+}
+
+impl<'a> Map<'a> {
+    pub fn create_test_map(graphics: &mut Graphics<'a>) -> Result<Map<'a>, AssetError> {
+        // two screens wide, so there is something to scroll to
+        let num_rows = (constants::SCREEN_HEIGHT / constants::TILE_SIZE) as usize;
+        let num_cols = 2 * (constants::SCREEN_WIDTH / constants::TILE_SIZE) as usize;
+        let mut map = Map {
+            tiles: vec![vec![Tile::empty(); num_cols]; num_rows],
+            crystals: Vec::new(),
+        };
+
+        let solid = Tile::new(
+            TileType::Solid,
+            Layer::Foreground,
+            Rc::new(Sprite::new(
+                graphics,
+                "content/TestTiles.bmp",
+                0,
+                0,
+                constants::TILE_SIZE,
+                constants::TILE_SIZE,
+            )?),
+        );
+        let backdrop = Tile::new(
+            TileType::Backdrop,
+            Layer::Tiles,
+            Rc::new(Sprite::new(
+                graphics,
+                "content/TestTiles.bmp",
+                constants::TILE_SIZE as i32,
+                0,
+                constants::TILE_SIZE,
+                constants::TILE_SIZE,
+            )?),
+        );
+
+        // floor and walls
+        for col in 0..num_cols {
+            map.tiles[11][col] = solid.clone();
+        }
+        for row in 0..11 {
+            map.tiles[row][0] = solid.clone();
+            map.tiles[row][num_cols - 1] = solid.clone();
+        }
+
+        // a step, a floating platform and some scenery behind it
+        map.tiles[10][5] = solid.clone();
+        map.tiles[9][6] = solid.clone();
+        map.tiles[10][6] = solid.clone();
+        for col in 10..14 {
+            map.tiles[7][col] = solid.clone();
+            map.tiles[8][col] = backdrop.clone();
+        }
+
+        // spikes between the platform and the staircase, the test tileset
+        // has no spike tile so they look like the backdrop
+        let spikes = Tile {
+            tile_type: TileType::Hazard,
+            ..backdrop.clone()
+        };
+        for col in 17..20 {
+            map.tiles[10][col] = spikes.clone();
+        }
+
+        // a staircase up to a ledge on the second screen
+        for step in 0..4 {
+            for row in 10 - step..11 {
+                map.tiles[row][24 + step] = solid.clone();
+            }
+        }
+        for col in 28..34 {
+            map.tiles[6][col] = solid.clone();
+        }
+
+        // crystals on the platform and the ledge
+        for &(row, col) in &[(6, 11), (6, 12), (5, 30), (5, 31), (5, 32)] {
+            map.crystals.push(tile_center(row, col));
+        }
+
+        Ok(map)
+    }
+
+    pub fn from_stage(
+        graphics: &mut Graphics<'a>,
+        stage: &Stage,
+        tileset: &str,
+    ) -> Result<Map<'a>, AssetError> {
+        let tile_size = constants::TILE_SIZE as i32;
+        let mut sprites: BTreeMap<u8, Rc<Sprite<'a>>> = BTreeMap::new();
+        let mut tiles = Vec::with_capacity(stage.layout.height);
+
+        for row in 0..stage.layout.height {
+            let mut tile_row = Vec::with_capacity(stage.layout.width);
+            for col in 0..stage.layout.width {
+                let index = stage.layout.tile(row, col);
+                let sprite = match sprites.entry(index) {
+                    Entry::Occupied(entry) => Rc::clone(entry.get()),
+                    Entry::Vacant(entry) => Rc::clone(entry.insert(Rc::new(Sprite::new(
+                        graphics,
+                        tileset,
+                        (index as i32 % TILESET_COLUMNS) * tile_size,
+                        (index as i32 / TILESET_COLUMNS) * tile_size,
+                        constants::TILE_SIZE,
+                        constants::TILE_SIZE,
+                    )?))),
+                };
+                let attribute = stage.attribute(row, col);
+                tile_row.push(Tile::new(
+                    tile_type(attribute),
+                    tile_layer(attribute),
+                    sprite,
+                ));
+            }
+            tiles.push(tile_row);
+        }
+
+        let crystals = stage
+            .entities
+            .iter()
+            .filter(|entity| entity.entity_type == CRYSTAL_ENTITY)
+            .map(|entity| tile_center(entity.y as i32, entity.x as i32))
+            .collect();
+
+        Ok(Map {
+            tiles: tiles,
+            crystals: crystals,
+        })
+    }
+
+    pub fn get_colliding_tiles(&self, rectangle: &Rectangle) -> Vec<CollisionTile> {
+        let tile_size = constants::TILE_SIZE as i32;
+        let first_row = rectangle.top().div_euclid(tile_size).max(0);
+        let last_row = rectangle
+            .bottom()
+            .div_euclid(tile_size)
+            .min(self.num_rows() - 1);
+        let first_col = rectangle.left().div_euclid(tile_size).max(0);
+        let last_col = rectangle
+            .right()
+            .div_euclid(tile_size)
+            .min(self.num_cols() - 1);
+
+        let mut collision_tiles = Vec::new();
+        for row in first_row..last_row + 1 {
+            for col in first_col..last_col + 1 {
+                collision_tiles.push(CollisionTile {
+                    row: row,
+                    col: col,
+                    tile_type: self.tiles[row as usize][col as usize].tile_type,
+                });
+            }
+        }
+        collision_tiles
+    }
+
+    /// Draws the tiles that are on screen.
+    pub fn draw(&self, graphics: &mut Graphics<'a>, camera: &Camera) {
+        let tile_size = constants::TILE_SIZE as i32;
+        let first_row = camera.top().div_euclid(tile_size).max(0);
+        let last_row = (camera.top() + constants::SCREEN_HEIGHT as i32)
+            .div_euclid(tile_size)
+            .min(self.num_rows() - 1);
+        let first_col = camera.left().div_euclid(tile_size).max(0);
+        let last_col = (camera.left() + constants::SCREEN_WIDTH as i32)
+            .div_euclid(tile_size)
+            .min(self.num_cols() - 1);
+
+        for row in first_row..last_row + 1 {
+            for col in first_col..last_col + 1 {
+                let tile = &self.tiles[row as usize][col as usize];
+                if let Some(ref sprite) = tile.sprite {
+                    let (x, y) = camera.to_screen(col * tile_size, row * tile_size);
+                    sprite.draw(graphics, Depth::new(tile.layer, 0), x, y);
+                }
+            }
+        }
+    }
+
+    /// Where experience crystals start out, their centers in world pixels.
+    pub fn crystals(&self) -> &[(i32, i32)] {
+        &self.crystals
+    }
+
+    /// Width of the map in pixels.
+    pub fn width(&self) -> i32 {
+        self.num_cols() * constants::TILE_SIZE as i32
+    }
+
+    /// Height of the map in pixels.
+    pub fn height(&self) -> i32 {
+        self.num_rows() * constants::TILE_SIZE as i32
+    }
+
+    fn num_rows(&self) -> i32 {
+        self.tiles.len() as i32
+    }
+
+    fn num_cols(&self) -> i32 {
+        self.tiles.first().map_or(0, |row| row.len() as i32)
+    }
+}
+
+fn tile_center(row: i32, col: i32) -> (i32, i32) {
+    let tile_size = constants::TILE_SIZE as i32;
+    (col * tile_size + tile_size / 2, row * tile_size + tile_size / 2)
+}
+
+/// Maps a tile attribute from a .pxa file onto how the tile behaves.
+fn tile_type(attribute: u8) -> TileType {
+    match attribute {
+        // solid, breakable, player-only solid and solid under water
+        0x41 | 0x43 | 0x46 | 0x61 => TileType::Solid,
+        // spikes, out of and under water
+        0x42 | 0x62 => TileType::Hazard,
+        _ => TileType::Backdrop,
+    }
+}
+
+/// Tiles with attributes from 0x40 up to 0x7f are drawn in front of entities.
+fn tile_layer(attribute: u8) -> Layer {
+    match attribute {
+        0x40..=0x7f => Layer::Foreground,
+        _ => Layer::Tiles,
+    }
+}
@@ -0,0 +1,81 @@
+use std::env;
+
+pub struct Options {
+    /// stage and tileset to load from the original game, e.g. `Cave Cave`
+    pub stage: Option<(String, String)>,
+    /// render to an off-screen surface instead of a window
+    pub headless: bool,
+    /// quit after this many frames
+    pub frames: Option<u32>,
+    /// write the input of every frame to this file
+    pub record: Option<String>,
+    /// play back input from this file instead of reading the keyboard
+    pub replay: Option<String>,
+    /// key bindings file to use instead of the default one
+    pub bindings: Option<String>,
+    /// how many times larger than the screen the window starts out
+    pub scale: u32,
+    /// which messages to log, see `LogFilter::parse`
+    pub log: Option<String>,
+    /// also write the log to `constants::LOG_FILE`
+    pub log_file: bool,
+    /// save every this many frames to `constants::FRAME_DUMP_DIRECTORY`
+    pub dump_frames: Option<u32>,
+}
+
+impl Options {
+    pub fn from_args() -> Result<Options, String> {
+        let mut options = Options {
+            stage: None,
+            headless: false,
+            frames: None,
+            record: None,
+            replay: None,
+            bindings: None,
+            scale: 1,
+            log: None,
+            log_file: false,
+            dump_frames: None,
+        };
+        let mut positional = Vec::new();
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => options.headless = true,
+                "--frames" => options.frames = Some(parse_value(&arg, args.next())?),
+                "--record" => options.record = Some(parse_value(&arg, args.next())?),
+                "--replay" => options.replay = Some(parse_value(&arg, args.next())?),
+                "--bindings" => options.bindings = Some(parse_value(&arg, args.next())?),
+                "--scale" => options.scale = parse_value(&arg, args.next())?,
+                "--log" => options.log = Some(parse_value(&arg, args.next())?),
+                "--log-file" => options.log_file = true,
+                "--dump-frames" => options.dump_frames = Some(parse_value(&arg, args.next())?),
+                _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+                _ => positional.push(arg),
+            }
+        }
+
+        if options.scale == 0 {
+            return Err("--scale must be at least 1".to_string());
+        }
+        if options.dump_frames == Some(0) {
+            return Err("--dump-frames must be at least 1".to_string());
+        }
+
+        let mut positional = positional.into_iter();
+        options.stage = match (positional.next(), positional.next()) {
+            (Some(stage), Some(tileset)) => Some((stage, tileset)),
+            (Some(stage), None) => Some((stage.clone(), stage)),
+            _ => None,
+        };
+
+        Ok(options)
+    }
+}
+
+fn parse_value<T: ::std::str::FromStr>(option: &str, value: Option<String>) -> Result<T, String> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("missing or invalid value for {}", option))
+}
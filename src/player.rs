@@ -1,8 +1,22 @@
+use animation::AnimationSet;
+use camera::Camera;
 use constants;
-use graphics::Graphics;
-use sprite::{AnimatedSprite, Sprite, UpdateAndDrawable};
+use graphics::{AssetError, Depth, Graphics, Layer, Transform};
+use map::{CollisionTile, Map, TileType};
+use projectile::Projectile;
+use rectangle::Rectangle;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sprite::UpdateAndDrawable;
 use std::collections::BTreeMap;
-use units::Milliseconds;
+use units::{Acceleration, Milliseconds, Position, Velocity, SUBPIXELS_PER_PIXEL};
+use weapon::{LevelChange, Weapon};
+
+// collision boxes relative to the top left of the sprite. The x box is wide
+// and short, the y box narrow and tall, so that walls and floors/ceilings are
+// each only detected by one of them
+const COLLISION_X: Rectangle = Rectangle::new(6, 10, 20, 12);
+const COLLISION_Y: Rectangle = Rectangle::new(10, 2, 12, 30);
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 enum MotionType {
@@ -13,12 +27,12 @@ enum MotionType {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
-enum HorizontalFacing {
+pub enum HorizontalFacing {
     Left,
     Right,
 }
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
-enum VerticalFacing {
+pub enum VerticalFacing {
     Up,
     Forward,
     Down,
@@ -44,6 +58,65 @@ impl SpriteState {
         }
     }
 
+    /// Parses names like `walking up`, as used in animation files. Sprites are
+    /// only defined facing left, facing right draws them mirrored.
+    fn from_name(name: &str) -> Option<SpriteState> {
+        let mut words = name.split_whitespace();
+        let motion_type = match words.next()? {
+            "standing" => MotionType::Standing,
+            "walking" => MotionType::Walking,
+            "jumping" => MotionType::Jumping,
+            "falling" => MotionType::Falling,
+            _ => return None,
+        };
+        let vertical_facing = match words.next()? {
+            "up" => VerticalFacing::Up,
+            "forward" => VerticalFacing::Forward,
+            "down" => VerticalFacing::Down,
+            _ => return None,
+        };
+        if words.next().is_some() {
+            return None;
+        }
+        Some(SpriteState::new(
+            motion_type,
+            HorizontalFacing::Left,
+            vertical_facing,
+        ))
+    }
+
+    /// The state whose sprite is drawn for this state.
+    fn sprite_key(self) -> SpriteState {
+        SpriteState {
+            horizontal_facing: HorizontalFacing::Left,
+            ..self
+        }
+    }
+
+    /// Every state that needs a sprite.
+    fn all_keys() -> Vec<SpriteState> {
+        let mut states = Vec::new();
+        for &motion_type in &[
+            MotionType::Standing,
+            MotionType::Walking,
+            MotionType::Jumping,
+            MotionType::Falling,
+        ] {
+            for &vertical_facing in &[
+                VerticalFacing::Up,
+                VerticalFacing::Forward,
+                VerticalFacing::Down,
+            ] {
+                states.push(SpriteState::new(
+                    motion_type,
+                    HorizontalFacing::Left,
+                    vertical_facing,
+                ));
+            }
+        }
+        states
+    }
+
     fn default() -> SpriteState {
         SpriteState {
             motion_type: MotionType::Standing,
@@ -95,94 +168,317 @@ impl Jump {
     }
 }
 
+/// Time after being hit during which the player can't be hurt again and
+/// flickers.
+struct Invincibility {
+    time_remaining: Milliseconds,
+}
+
+impl Invincibility {
+    fn new() -> Invincibility {
+        Invincibility {
+            time_remaining: Milliseconds::new(0),
+        }
+    }
+
+    fn update(&mut self, elapsed_time: Milliseconds) {
+        if self.time_remaining > elapsed_time {
+            self.time_remaining -= elapsed_time;
+        } else {
+            self.time_remaining = Milliseconds::new(0);
+        }
+    }
+
+    fn active(&self) -> bool {
+        self.time_remaining > Milliseconds::new(0)
+    }
+
+    fn start(&mut self) {
+        self.time_remaining = Milliseconds::new(constants::INVINCIBILITY_TIME);
+    }
+
+    /// Whether the player is drawn, every other flash is skipped while
+    /// invincible.
+    fn visible(&self) -> bool {
+        (self.time_remaining.value() / constants::INVINCIBILITY_FLASH_TIME) & 1 == 0
+    }
+}
+
 pub struct Player<'a> {
-    sprites: BTreeMap<SpriteState, Box<UpdateAndDrawable + 'a>>,
+    sprites: BTreeMap<SpriteState, Box<UpdateAndDrawable<'a> + 'a>>,
     sprite_state: SpriteState,
-    x: i32,
-    y: i32,
-    velocity_x: f32,
-    acceleration_x: f32,
-    velocity_y: f32,
+    // the state whose sprite was animated during the last update
+    animated_state: SpriteState,
+    x: Position,
+    y: Position,
+    // where the last update moved the player from
+    previous_x: Position,
+    previous_y: Position,
+    velocity_x: Velocity,
+    acceleration_x: Acceleration,
+    velocity_y: Velocity,
     is_on_ground: bool,
     jump: Jump,
+    health: u32,
+    invincibility: Invincibility,
+    weapon: Weapon<'a>,
 }
 
 impl<'a> Player<'a> {
-    pub fn new(graphics: &mut Graphics<'a>, x: i32, y: i32) -> Player<'a> {
-        Player {
-            sprites: Player::create_sprite_map(graphics),
+    pub fn new(graphics: &mut Graphics<'a>, x: i32, y: i32) -> Result<Player<'a>, AssetError> {
+        Ok(Player {
+            sprites: Player::create_sprite_map(graphics)?,
             sprite_state: SpriteState::default(),
-            x: x,
-            y: y,
-            velocity_x: 0.0,
-            acceleration_x: 0.0,
-            velocity_y: 0.0,
+            animated_state: SpriteState::default(),
+            x: Position::from_pixels(x),
+            y: Position::from_pixels(y),
+            previous_x: Position::from_pixels(x),
+            previous_y: Position::from_pixels(y),
+            velocity_x: Velocity::new(0),
+            acceleration_x: Acceleration::new(0),
+            velocity_y: Velocity::new(0),
             is_on_ground: true,
             jump: Jump::new(),
-        }
+            health: constants::PLAYER_MAX_HEALTH,
+            invincibility: Invincibility::new(),
+            weapon: Weapon::polar_star(graphics)?,
+        })
     }
 
-    pub fn draw(&self, graphics: &mut Graphics) {
-        let sprite = self.sprites.get(&SpriteState::new(MotionType::Falling, HorizontalFacing::Left, VerticalFacing::Forward)).expect("Lookup of sprite for sprite state");
-        sprite.draw(graphics, 320, 200);
-        let sprite = self.sprites.get(&SpriteState::new(MotionType::Falling, HorizontalFacing::Left, VerticalFacing::Up)).expect("Lookup of sprite for sprite state");
-        sprite.draw(graphics, 320, 240);
-        let sprite = self.sprites.get(&SpriteState::new(MotionType::Falling, HorizontalFacing::Left, VerticalFacing::Down)).expect("Lookup of sprite for sprite state");
-        sprite.draw(graphics, 320, 280);
-        let sprite = self.sprites.get(&self.sprite_state).expect("Lookup of sprite for sprite state");
-        sprite.draw(graphics, self.x, self.y);
+    pub fn draw(&self, graphics: &mut Graphics<'a>, camera: &Camera, alpha: f32) {
+        if !self.invincibility.visible() {
+            return;
+        }
+        let sprite = self
+            .sprites
+            .get(&self.sprite_state.sprite_key())
+            .expect("Lookup of sprite for sprite state");
+        let (x, y) = camera.to_screen(
+            self.x.interpolate(self.previous_x, alpha),
+            self.y.interpolate(self.previous_y, alpha),
+        );
+        let transform = Transform {
+            flip_horizontal: self.sprite_state.horizontal_facing == HorizontalFacing::Right,
+            ..Transform::none()
+        };
+        sprite.draw_transformed(graphics, Depth::new(Layer::Entities, 0), x, y, transform);
+        self.weapon.draw(
+            graphics,
+            x,
+            y,
+            self.sprite_state.horizontal_facing,
+            self.gun_facing(),
+        );
     }
 
-    pub fn update(&mut self, elapsed_time: Milliseconds) {
+    /// Advances the player by a single simulation step. Velocities are per step,
+    /// so `elapsed_time` should always be the step length. Returns how the
+    /// weapon's level changed from getting hurt.
+    pub fn update(&mut self, elapsed_time: Milliseconds, map: &Map) -> Option<LevelChange> {
+        self.previous_x = self.x;
+        self.previous_y = self.y;
+
         // update jump state
         self.jump.update(elapsed_time);
+        self.invincibility.update(elapsed_time);
+
+        self.update_x(map);
+        self.update_y(map);
+
+        // touching a hazard hurts, throwing the player away from it
+        let mut level_change = None;
+        if let Some(tile) = map
+            .get_colliding_tiles(&self.hit_box())
+            .into_iter()
+            .find(|tile| tile.tile_type == TileType::Hazard)
+        {
+            let tile_size = constants::TILE_SIZE as i32;
+            level_change = self.take_damage(
+                constants::HAZARD_DAMAGE,
+                tile.col * tile_size + tile_size / 2,
+            );
+        }
+
+        // update sprite state
+        self.update_sprite_state();
 
-        // update x position, velocity
-        self.x += (self.velocity_x * elapsed_time.value() as f32).round() as i32;
-        if self.acceleration_x < 0.0 {
-            self.velocity_x = (-constants::MAX_SPEED)
-                .max(self.velocity_x + self.acceleration_x * elapsed_time.value() as f32)
-        } else if self.acceleration_x > 0.0 {
-            self.velocity_x = constants::MAX_SPEED
-                .min(self.velocity_x + self.acceleration_x * elapsed_time.value() as f32)
+        // update the sprite, animations start from their first frame whenever
+        // the state changes
+        if let Some(sprite) = self.sprites.get_mut(&self.sprite_state.sprite_key()) {
+            if self.sprite_state != self.animated_state {
+                sprite.reset();
+                self.animated_state = self.sprite_state;
+            }
+            sprite.update(elapsed_time);
+        }
+        level_change
+    }
+
+    fn update_x(&mut self, map: &Map) {
+        // update x velocity
+        let target_x = self.x + self.velocity_x;
+        if self.acceleration_x < Acceleration::new(0) {
+            self.velocity_x = (-constants::MAX_SPEED).max(self.velocity_x + self.acceleration_x)
+        } else if self.acceleration_x > Acceleration::new(0) {
+            self.velocity_x = constants::MAX_SPEED.min(self.velocity_x + self.acceleration_x)
         } else {
             if self.on_ground() {
-                self.velocity_x *= constants::SLOW_DOWN;
+                let (numerator, denominator) = constants::SLOW_DOWN;
+                self.velocity_x = self.velocity_x.scale(numerator, denominator);
+            }
+        }
+
+        // move, stopping at the first wall in the direction of motion, then
+        // make sure we are not stuck in a wall on the other side
+        let tile_size = constants::TILE_SIZE as i32;
+        let delta = target_x.to_pixels() - self.x.to_pixels();
+        if delta > 0 {
+            if let Some(tile) = solid_tile(map, &self.right_collision(delta), Side::Right) {
+                self.x = Position::from_pixels(tile.col * tile_size - COLLISION_X.right());
+                self.velocity_x = Velocity::new(0);
+            } else {
+                self.x = target_x;
+            }
+            if let Some(tile) = solid_tile(map, &self.left_collision(0), Side::Left) {
+                self.x = Position::from_pixels((tile.col + 1) * tile_size - COLLISION_X.left());
+            }
+        } else {
+            if let Some(tile) = solid_tile(map, &self.left_collision(delta), Side::Left) {
+                self.x = Position::from_pixels((tile.col + 1) * tile_size - COLLISION_X.left());
+                self.velocity_x = Velocity::new(0);
+            } else {
+                self.x = target_x;
+            }
+            if let Some(tile) = solid_tile(map, &self.right_collision(0), Side::Right) {
+                self.x = Position::from_pixels(tile.col * tile_size - COLLISION_X.right());
             }
         }
+    }
 
-        // update y position, velocity
-        self.y += (self.velocity_y * elapsed_time.value() as f32).round() as i32;
+    fn update_y(&mut self, map: &Map) {
+        // update y velocity
+        let target_y = self.y + self.velocity_y;
         if !self.jump.active() {
-            self.velocity_y = (self.velocity_y
-                + (constants::GRAVITY * elapsed_time.value() as f32))
-                .min(constants::MAX_SPEED_Y);
+            self.velocity_y = (self.velocity_y + constants::GRAVITY).min(constants::MAX_SPEED_Y);
         }
 
-        // determine if on ground
-        if self.y >= 320 {
-            self.y = 320;
-            self.velocity_y = 0.0;
-            self.is_on_ground = true;
+        // same as for x, but landing on a tile puts us on the ground
+        let tile_size = constants::TILE_SIZE as i32;
+        let delta = target_y.to_pixels() - self.y.to_pixels();
+        if delta > 0 {
+            if let Some(tile) = solid_tile(map, &self.bottom_collision(delta), Side::Bottom) {
+                self.y = Position::from_pixels(tile.row * tile_size - COLLISION_Y.bottom());
+                self.velocity_y = Velocity::new(0);
+                self.is_on_ground = true;
+            } else {
+                self.y = target_y;
+                self.is_on_ground = false;
+            }
+            if let Some(tile) = solid_tile(map, &self.top_collision(0), Side::Top) {
+                self.y = Position::from_pixels((tile.row + 1) * tile_size - COLLISION_Y.top());
+            }
         } else {
-            self.is_on_ground = false;
+            if let Some(tile) = solid_tile(map, &self.top_collision(delta), Side::Top) {
+                self.y = Position::from_pixels((tile.row + 1) * tile_size - COLLISION_Y.top());
+                self.velocity_y = Velocity::new(0);
+            } else {
+                self.y = target_y;
+                self.is_on_ground = false;
+            }
+            if let Some(tile) = solid_tile(map, &self.bottom_collision(0), Side::Bottom) {
+                self.y = Position::from_pixels(tile.row * tile_size - COLLISION_Y.bottom());
+                self.velocity_y = Velocity::new(0);
+                self.is_on_ground = true;
+            }
         }
+    }
 
-        // update sprite state
-        self.update_sprite_state();
+    fn left_collision(&self, delta: i32) -> Rectangle {
+        Rectangle::new(
+            self.x.to_pixels() + COLLISION_X.left() + delta,
+            self.y.to_pixels() + COLLISION_X.top(),
+            COLLISION_X.width() / 2 - delta,
+            COLLISION_X.height(),
+        )
+    }
 
+    fn right_collision(&self, delta: i32) -> Rectangle {
+        Rectangle::new(
+            self.x.to_pixels() + COLLISION_X.left() + COLLISION_X.width() / 2,
+            self.y.to_pixels() + COLLISION_X.top(),
+            COLLISION_X.width() / 2 + delta,
+            COLLISION_X.height(),
+        )
+    }
 
-        // update the sprite
-        if let Some(sprite) = self.sprites.get_mut(&self.sprite_state) {
-            sprite.update(elapsed_time);
+    fn top_collision(&self, delta: i32) -> Rectangle {
+        Rectangle::new(
+            self.x.to_pixels() + COLLISION_Y.left(),
+            self.y.to_pixels() + COLLISION_Y.top() + delta,
+            COLLISION_Y.width(),
+            COLLISION_Y.height() / 2 - delta,
+        )
+    }
+
+    fn bottom_collision(&self, delta: i32) -> Rectangle {
+        Rectangle::new(
+            self.x.to_pixels() + COLLISION_Y.left(),
+            self.y.to_pixels() + COLLISION_Y.top() + COLLISION_Y.height() / 2,
+            COLLISION_Y.width(),
+            COLLISION_Y.height() / 2 + delta,
+        )
+    }
+
+    /// Where the player gets hurt and picks things up, the area covered by
+    /// both collision boxes.
+    pub fn hit_box(&self) -> Rectangle {
+        let left = COLLISION_X.left().min(COLLISION_Y.left());
+        let top = COLLISION_X.top().min(COLLISION_Y.top());
+        Rectangle::new(
+            self.x.to_pixels() + left,
+            self.y.to_pixels() + top,
+            COLLISION_X.right().max(COLLISION_Y.right()) - left,
+            COLLISION_X.bottom().max(COLLISION_Y.bottom()) - top,
+        )
+    }
+
+    /// Loses `damage` health and weapon experience and gets knocked back away
+    /// from `source_x`, in world pixels. Nothing happens while invincible from
+    /// the last hit. Returns how the weapon's level changed.
+    pub fn take_damage(&mut self, damage: u32, source_x: i32) -> Option<LevelChange> {
+        if self.invincibility.active() || self.is_dead() {
+            return None;
         }
+        self.health = self.health.saturating_sub(damage);
+        self.invincibility.start();
+
+        self.jump.deactivate();
+        self.velocity_y = -constants::KNOCKBACK_SPEED_Y;
+        self.velocity_x = if source_x > self.center().0 {
+            -constants::KNOCKBACK_SPEED_X
+        } else {
+            constants::KNOCKBACK_SPEED_X
+        };
+
+        self.weapon
+            .experience_mut()
+            .lose(damage * constants::EXPERIENCE_LOST_PER_DAMAGE)
+    }
+
+    /// Adds experience to the weapon, returning how its level changed.
+    pub fn collect_experience(&mut self, points: u32) -> Option<LevelChange> {
+        self.weapon.experience_mut().gain(points)
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.health == 0
     }
 
     fn update_sprite_state(&mut self) {
-        if self.acceleration_x < 0.0 {
+        if self.acceleration_x < Acceleration::new(0) {
             self.sprite_state.motion_type = MotionType::Walking;
             self.sprite_state.horizontal_facing = HorizontalFacing::Left;
-        } else if self.acceleration_x > 0.0 {
+        } else if self.acceleration_x > Acceleration::new(0) {
             self.sprite_state.motion_type = MotionType::Walking;
             self.sprite_state.horizontal_facing = HorizontalFacing::Right;
         } else {
@@ -190,14 +486,12 @@ impl<'a> Player<'a> {
         }
 
         if !self.is_on_ground {
-            if self.velocity_y < 0.0 {
+            if self.velocity_y < Velocity::new(0) {
                 self.sprite_state.motion_type = MotionType::Jumping;
-            } else if self.velocity_y > 0.0 {
+            } else if self.velocity_y > Velocity::new(0) {
                 self.sprite_state.motion_type = MotionType::Falling;
             }
         }
-        //println!("{:?}", self.sprite_state);
-
     }
 
     pub fn start_moving_left(&mut self) {
@@ -209,7 +503,7 @@ impl<'a> Player<'a> {
     }
 
     pub fn stop_moving(&mut self) {
-        self.acceleration_x = 0.0;
+        self.acceleration_x = Acceleration::new(0);
     }
 
     pub fn look_horizontal(&mut self) {
@@ -224,6 +518,92 @@ impl<'a> Player<'a> {
         self.sprite_state.vertical_facing = VerticalFacing::Down;
     }
 
+    /// Which way the gun points. Like in the original, it only points down
+    /// while in the air.
+    fn gun_facing(&self) -> VerticalFacing {
+        match self.sprite_state.vertical_facing {
+            VerticalFacing::Down if self.is_on_ground => VerticalFacing::Forward,
+            facing => facing,
+        }
+    }
+
+    /// Shoots the weapon, unless `active` of its projectiles are already as
+    /// many as it allows at once.
+    pub fn fire(&self, active: usize) -> Option<Projectile<'a>> {
+        self.weapon.fire(
+            self.x.to_pixels(),
+            self.y.to_pixels(),
+            self.sprite_state.horizontal_facing,
+            self.gun_facing(),
+            active,
+        )
+    }
+
+    pub fn weapon(&self) -> &Weapon<'a> {
+        &self.weapon
+    }
+
+    /// Center of the sprite in world pixels.
+    pub fn center(&self) -> (i32, i32) {
+        let half_tile = constants::TILE_SIZE as i32 / 2;
+        (self.x.to_pixels() + half_tile, self.y.to_pixels() + half_tile)
+    }
+
+    /// A few lines about the player's state for the debug overlay.
+    pub fn debug_text(&self) -> String {
+        let subpixels = SUBPIXELS_PER_PIXEL as f32;
+        format!(
+            "position: {:.2}, {:.2}\n\
+             velocity: {}, {} / step\n\
+             state: {:?} {:?} {:?}\n\
+             on ground: {}\n\
+             health: {}/{}, invincible for {}ms",
+            self.x.value() as f32 / subpixels,
+            self.y.value() as f32 / subpixels,
+            self.velocity_x.value(),
+            self.velocity_y.value(),
+            self.sprite_state.motion_type,
+            self.sprite_state.horizontal_facing,
+            self.sprite_state.vertical_facing,
+            self.is_on_ground,
+            self.health,
+            constants::PLAYER_MAX_HEALTH,
+            self.invincibility.time_remaining.value()
+        )
+    }
+
+    /// Outlines the boxes used for colliding with walls and floors.
+    pub fn draw_collision_boxes(&self, graphics: &mut Graphics, camera: &Camera, alpha: f32) {
+        let (x, y) = camera.to_screen(
+            self.x.interpolate(self.previous_x, alpha),
+            self.y.interpolate(self.previous_y, alpha),
+        );
+        let depth = Depth::new(Layer::Hud, 0);
+        for &(collision, color) in &[
+            (COLLISION_X, Color::RGB(0xff, 0, 0)),
+            (COLLISION_Y, Color::RGB(0, 0xff, 0)),
+        ] {
+            graphics.draw_rect(
+                Rect::new(
+                    x + collision.left(),
+                    y + collision.top(),
+                    collision.width() as u32,
+                    collision.height() as u32,
+                ),
+                color,
+                depth,
+            );
+        }
+    }
+
+    pub fn horizontal_facing(&self) -> HorizontalFacing {
+        self.sprite_state.horizontal_facing
+    }
+
+    pub fn vertical_facing(&self) -> VerticalFacing {
+        self.sprite_state.vertical_facing
+    }
+
     pub fn on_ground(&self) -> bool {
         self.is_on_ground
     }
@@ -232,7 +612,7 @@ impl<'a> Player<'a> {
         if self.is_on_ground {
             self.jump.reset();
             self.velocity_y = -constants::JUMP_SPEED;
-        } else if self.velocity_y < 0.0 {
+        } else if self.velocity_y < Velocity::new(0) {
             self.jump.reactivate();
         }
     }
@@ -243,64 +623,191 @@ impl<'a> Player<'a> {
 
     fn create_sprite_map<'b>(
         graphics: &mut Graphics<'b>,
-    ) -> BTreeMap<SpriteState, Box<UpdateAndDrawable + 'b>> {
-        let mut map: BTreeMap<SpriteState, Box<UpdateAndDrawable + 'b>> = BTreeMap::new();
+    ) -> Result<BTreeMap<SpriteState, Box<UpdateAndDrawable<'b> + 'b>>, AssetError> {
+        let animations = AnimationSet::load(constants::PLAYER_ANIMATIONS, SpriteState::from_name)?;
+        let mut map: BTreeMap<SpriteState, Box<UpdateAndDrawable<'b> + 'b>> = BTreeMap::new();
+        for &(state, ref animation) in &animations.animations {
+            map.insert(state, animation.load_sprite(graphics, &animations.sheet)?);
+        }
 
-        // load the 11th character in the sprite sheet
-        Player::load_character_sprites(graphics, &mut map, 10); 
+        // every state the player can get into needs something to draw
+        if let Some(state) = SpriteState::all_keys()
+            .into_iter()
+            .find(|state| !map.contains_key(state))
+        {
+            return Err(AssetError::Invalid(
+                constants::PLAYER_ANIMATIONS.to_string(),
+                format!("no animation for {:?}", state),
+            ));
+        }
 
-        map
+        Ok(map)
     }
+}
 
-    fn load_character_sprites<'b>(
-        graphics: &mut Graphics<'b>,
-        map: &mut BTreeMap<SpriteState, Box<UpdateAndDrawable + 'b>>,
-        nth_character: u32,
-    ) {
-        Player::load_motion_sprites(graphics, map, 0, (nth_character * 2) as i32, HorizontalFacing::Left, VerticalFacing::Forward);
-        Player::load_motion_sprites(graphics, map, 3, (nth_character * 2) as i32, HorizontalFacing::Left, VerticalFacing::Up);
-        Player::load_motion_sprites(graphics, map, 6, (nth_character * 2) as i32, HorizontalFacing::Left, VerticalFacing::Down);
-        Player::load_motion_sprites(graphics, map, 0, (nth_character * 2 + 1) as i32, HorizontalFacing::Right, VerticalFacing::Forward);
-        Player::load_motion_sprites(graphics, map, 3, (nth_character * 2 + 1) as i32, HorizontalFacing::Right, VerticalFacing::Up);
-        Player::load_motion_sprites(graphics, map, 6, (nth_character * 2 + 1) as i32, HorizontalFacing::Right, VerticalFacing::Down);
-    }
-
-    fn load_motion_sprites<'b>(
-        graphics: &mut Graphics<'b>,
-        map: &mut BTreeMap<SpriteState, Box<UpdateAndDrawable + 'b>>,
-        x_tile_offset: i32,
-        y_tile_offset: i32,
-        horizontal_facing: HorizontalFacing,
-        vertical_facing: VerticalFacing,
-    ) {
-        map.insert(
-            SpriteState::new(MotionType::Walking, horizontal_facing, vertical_facing),
-            Box::new(AnimatedSprite::new(
-                graphics,
-                "content/MyChar.bmp",
-                x_tile_offset * constants::TILE_SIZE as i32,
-                y_tile_offset * constants::TILE_SIZE as i32,
-                constants::TILE_SIZE,
-                constants::TILE_SIZE,
-                10,
-                3,
-            )),
-        );
-        let mut insert_sprite = |motion_type, offset| {
-            map.insert(
-                SpriteState::new(motion_type, horizontal_facing, vertical_facing),
-                Box::new(Sprite::new(
-                    graphics,
-                    "content/MyChar.bmp",
-                    (x_tile_offset + offset) * constants::TILE_SIZE as i32,
-                    y_tile_offset * constants::TILE_SIZE as i32,
-                    constants::TILE_SIZE,
-                    constants::TILE_SIZE,
-                )),
-            );
+/// The side of the player a collision box is on, and so the direction it is
+/// moved in.
+#[derive(Clone, Copy, Debug)]
+enum Side {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+fn solid_tile(map: &Map, rectangle: &Rectangle, side: Side) -> Option<CollisionTile> {
+    nearest_solid(map.get_colliding_tiles(rectangle), side)
+}
+
+/// The solid tile that is hit first moving towards `side`, when a collision
+/// box spans several of them.
+fn nearest_solid(tiles: Vec<CollisionTile>, side: Side) -> Option<CollisionTile> {
+    tiles
+        .into_iter()
+        .filter(|tile| tile.tile_type == TileType::Solid)
+        .min_by_key(|tile| match side {
+            Side::Left => -tile.col,
+            Side::Right => tile.col,
+            Side::Top => -tile.row,
+            Side::Bottom => tile.row,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics::with_test_graphics;
+
+    fn tile(row: i32, col: i32, tile_type: TileType) -> CollisionTile {
+        CollisionTile {
+            row: row,
+            col: col,
+            tile_type: tile_type,
+        }
+    }
+
+    #[test]
+    fn invincibility_wears_off() {
+        let mut invincibility = Invincibility::new();
+        assert!(!invincibility.active());
+        invincibility.start();
+        invincibility.update(Milliseconds::new(constants::INVINCIBILITY_TIME - 1));
+        assert!(invincibility.active());
+        invincibility.update(Milliseconds::new(1));
+        assert!(!invincibility.active());
+        invincibility.update(Milliseconds::new(100));
+        assert!(!invincibility.active());
+    }
+
+    #[test]
+    fn flickers_while_invincible() {
+        let flash = Milliseconds::new(constants::INVINCIBILITY_FLASH_TIME);
+        let mut invincibility = Invincibility::new();
+        assert!(invincibility.visible());
+        invincibility.start();
+        let mut visible = Vec::new();
+        for _ in 0..4 {
+            visible.push(invincibility.visible());
+            invincibility.update(flash);
+        }
+        assert_eq!(visible, vec![true, false, true, false]);
+
+        invincibility.update(Milliseconds::new(constants::INVINCIBILITY_TIME));
+        assert!(invincibility.visible());
+    }
+
+    #[test]
+    fn takes_damage() {
+        with_test_graphics(|graphics| {
+            let mut player = Player::new(graphics, 0, 0).unwrap();
+            let (center_x, _) = player.center();
+
+            // hit from the right, knocked back to the left
+            player.take_damage(1, center_x + 10);
+            assert_eq!(player.health, constants::PLAYER_MAX_HEALTH - 1);
+            assert!(player.invincibility.active());
+            assert_eq!(player.velocity_x, -constants::KNOCKBACK_SPEED_X);
+            assert_eq!(player.velocity_y, -constants::KNOCKBACK_SPEED_Y);
+
+            // no damage until the invincibility wore off
+            player.take_damage(1, center_x - 10);
+            assert_eq!(player.health, constants::PLAYER_MAX_HEALTH - 1);
+            player
+                .invincibility
+                .update(Milliseconds::new(constants::INVINCIBILITY_TIME));
+            player.take_damage(1, center_x - 10);
+            assert_eq!(player.health, constants::PLAYER_MAX_HEALTH - 2);
+            assert_eq!(player.velocity_x, constants::KNOCKBACK_SPEED_X);
+        });
+    }
+
+    #[test]
+    fn dies_when_out_of_health() {
+        with_test_graphics(|graphics| {
+            let mut player = Player::new(graphics, 0, 0).unwrap();
+
+            player.take_damage(constants::PLAYER_MAX_HEALTH + 10, 0);
+            assert_eq!(player.health, 0);
+            assert!(player.is_dead());
+            // the dead aren't hurt anymore
+            player
+                .invincibility
+                .update(Milliseconds::new(constants::INVINCIBILITY_TIME));
+            player.take_damage(1, 0);
+            assert_eq!(player.health, 0);
+            assert!(!player.invincibility.active());
+        });
+    }
+
+    #[test]
+    fn stands_still_on_the_ground() {
+        with_test_graphics(|graphics| {
+            let map = Map::create_test_map(graphics).unwrap();
+            // on the floor of the test map
+            let mut player = Player::new(graphics, 320, 320).unwrap();
+
+            // gravity adds less than a pixel per update, which mustn't build up
+            for _ in 0..20 {
+                player.update(Milliseconds::new(constants::UPDATE_STEP), &map);
+                assert!(player.on_ground());
+                assert_eq!(player.velocity_y, Velocity::new(0));
+                assert_eq!(player.y.to_pixels(), 320);
+            }
+        });
+    }
+
+    #[test]
+    fn finds_nearest_solid_tile() {
+        // a 3x3 block of tiles with the middle one empty
+        let tiles = || {
+            let mut tiles = Vec::new();
+            for row in 0..3 {
+                for col in 0..3 {
+                    let tile_type = if (row, col) == (1, 1) {
+                        TileType::Empty
+                    } else {
+                        TileType::Solid
+                    };
+                    tiles.push(tile(row, col, tile_type));
+                }
+            }
+            tiles
         };
-        insert_sprite(MotionType::Standing, 0);
-        insert_sprite(MotionType::Jumping, 1);
-        insert_sprite(MotionType::Falling, 2);
+        let nearest = |side| nearest_solid(tiles(), side).map(|tile| (tile.row, tile.col));
+        assert_eq!(nearest(Side::Left).map(|(_, col)| col), Some(2));
+        assert_eq!(nearest(Side::Right).map(|(_, col)| col), Some(0));
+        assert_eq!(nearest(Side::Top).map(|(row, _)| row), Some(2));
+        assert_eq!(nearest(Side::Bottom).map(|(row, _)| row), Some(0));
+    }
+
+    #[test]
+    fn ignores_tiles_that_are_not_solid() {
+        let tiles = vec![
+            tile(0, 0, TileType::Solid),
+            tile(0, 1, TileType::Hazard),
+            tile(0, 2, TileType::Backdrop),
+        ];
+        assert_eq!(nearest_solid(tiles.clone(), Side::Left), Some(tiles[0]));
+        assert_eq!(nearest_solid(vec![tiles[1], tiles[2]], Side::Left), None);
     }
 }
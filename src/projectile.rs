@@ -0,0 +1,262 @@
+use camera::Camera;
+use constants;
+use graphics::{Depth, Graphics, Layer, Transform};
+use map::{Map, TileType};
+use player::{HorizontalFacing, VerticalFacing};
+use rectangle::Rectangle;
+use sprite::{AnimatedSprite, Drawable, Sprite, Updatable};
+use std::rc::Rc;
+use units::{Milliseconds, Position, Velocity};
+
+// half the size of the square around a projectile's center that hits walls
+const HIT_SIZE: i32 = 2;
+
+/// What happened to a projectile during an update.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flight {
+    Flying,
+    /// went as far as it can without hitting anything
+    Expired,
+    /// hit a wall at the given world pixel
+    HitWall(i32, i32),
+}
+
+/// A shot flying in a straight line until it hits a wall or runs out of range.
+/// Positions are of its center.
+pub struct Projectile<'a> {
+    sprite: Rc<Sprite<'a>>,
+    transform: Transform,
+    x: Position,
+    y: Position,
+    // where the last update moved it from, the range is used up by the
+    // distance between the two
+    previous_x: Position,
+    previous_y: Position,
+    velocity_x: Velocity,
+    velocity_y: Velocity,
+    // pixels left to travel
+    range: i32,
+}
+
+impl<'a> Projectile<'a> {
+    /// Creates a projectile at `x`, `y` in world pixels. Up and down win over
+    /// the horizontal direction. The sprite is of a projectile flying left like
+    /// the gun points, it is mirrored and rotated for other directions.
+    pub fn new(
+        sprite: Rc<Sprite<'a>>,
+        x: i32,
+        y: i32,
+        horizontal: HorizontalFacing,
+        vertical: VerticalFacing,
+        range: i32,
+    ) -> Projectile<'a> {
+        let speed = constants::PROJECTILE_SPEED;
+        let zero = Velocity::new(0);
+        // rotations are clockwise, which turns left into up
+        let (velocity_x, velocity_y, flip_horizontal, angle) = match (horizontal, vertical) {
+            (_, VerticalFacing::Up) => (zero, -speed, false, 90.0),
+            (_, VerticalFacing::Down) => (zero, speed, false, 270.0),
+            (HorizontalFacing::Left, VerticalFacing::Forward) => (-speed, zero, false, 0.0),
+            (HorizontalFacing::Right, VerticalFacing::Forward) => (speed, zero, true, 0.0),
+        };
+        Projectile {
+            sprite: sprite,
+            transform: Transform {
+                flip_horizontal: flip_horizontal,
+                angle: angle,
+                ..Transform::none()
+            },
+            x: Position::from_pixels(x),
+            y: Position::from_pixels(y),
+            previous_x: Position::from_pixels(x),
+            previous_y: Position::from_pixels(y),
+            velocity_x: velocity_x,
+            velocity_y: velocity_y,
+            range: range,
+        }
+    }
+
+    /// Moves by a single simulation step.
+    pub fn update(&mut self, map: &Map) -> Flight {
+        self.previous_x = self.x;
+        self.previous_y = self.y;
+        self.x += self.velocity_x;
+        self.y += self.velocity_y;
+        self.range -= (self.x.to_pixels() - self.previous_x.to_pixels()).abs()
+            + (self.y.to_pixels() - self.previous_y.to_pixels()).abs();
+
+        let (x, y) = (self.x.to_pixels(), self.y.to_pixels());
+        let hit_box = Rectangle::new(x - HIT_SIZE, y - HIT_SIZE, 2 * HIT_SIZE, 2 * HIT_SIZE);
+        if map
+            .get_colliding_tiles(&hit_box)
+            .iter()
+            .any(|tile| tile.tile_type == TileType::Solid)
+        {
+            Flight::HitWall(x, y)
+        } else if self.range <= 0 {
+            Flight::Expired
+        } else {
+            Flight::Flying
+        }
+    }
+
+    pub fn draw(&self, graphics: &mut Graphics<'a>, camera: &Camera, alpha: f32) {
+        let half_tile = constants::TILE_SIZE as i32 / 2;
+        let (x, y) = camera.to_screen(
+            self.x.interpolate(self.previous_x, alpha) - half_tile,
+            self.y.interpolate(self.previous_y, alpha) - half_tile,
+        );
+        self.sprite.draw_transformed(
+            graphics,
+            Depth::new(Layer::Entities, 1),
+            x,
+            y,
+            self.transform,
+        );
+    }
+}
+
+/// The effect shown where a projectile hit something, it goes away once its
+/// animation is over.
+#[derive(Clone)]
+pub struct Impact<'a> {
+    sprite: AnimatedSprite<'a>,
+    x: i32,
+    y: i32,
+}
+
+impl<'a> Impact<'a> {
+    /// `sprite` should play once, the impact is centered on `x`, `y` in world
+    /// pixels.
+    pub fn new(sprite: AnimatedSprite<'a>, x: i32, y: i32) -> Impact<'a> {
+        Impact {
+            sprite: sprite,
+            x: x,
+            y: y,
+        }
+    }
+
+    pub fn update(&mut self, elapsed_time: Milliseconds) {
+        self.sprite.update(elapsed_time);
+    }
+
+    pub fn finished(&self) -> bool {
+        self.sprite.finished()
+    }
+
+    pub fn draw(&self, graphics: &mut Graphics<'a>, camera: &Camera) {
+        let half_tile = constants::TILE_SIZE as i32 / 2;
+        let (x, y) = camera.to_screen(self.x - half_tile, self.y - half_tile);
+        self.sprite
+            .draw(graphics, Depth::new(Layer::Entities, 2), x, y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics::with_test_graphics;
+    use units::SUBPIXELS_PER_PIXEL;
+
+    fn shoot<'a>(
+        graphics: &mut Graphics<'a>,
+        x: i32,
+        y: i32,
+        horizontal: HorizontalFacing,
+        vertical: VerticalFacing,
+        range: i32,
+    ) -> Projectile<'a> {
+        let tile_size = constants::TILE_SIZE;
+        let sprite = Sprite::new(
+            graphics,
+            constants::BULLET_SHEET,
+            0,
+            0,
+            tile_size,
+            tile_size,
+        );
+        Projectile::new(Rc::new(sprite.unwrap()), x, y, horizontal, vertical, range)
+    }
+
+    #[test]
+    fn points_where_it_flies() {
+        with_test_graphics(|graphics| {
+            let mut transform = |horizontal, vertical| {
+                let projectile = shoot(graphics, 0, 0, horizontal, vertical, 100);
+                (
+                    projectile.transform.flip_horizontal,
+                    projectile.transform.angle,
+                )
+            };
+            let forward = VerticalFacing::Forward;
+            assert_eq!(transform(HorizontalFacing::Left, forward), (false, 0.0));
+            assert_eq!(transform(HorizontalFacing::Right, forward), (true, 0.0));
+            for &horizontal in &[HorizontalFacing::Left, HorizontalFacing::Right] {
+                assert_eq!(transform(horizontal, VerticalFacing::Up), (false, 90.0));
+                assert_eq!(transform(horizontal, VerticalFacing::Down), (false, 270.0));
+            }
+        });
+    }
+
+    #[test]
+    fn expires_after_its_range() {
+        with_test_graphics(|graphics| {
+            let map = Map::create_test_map(graphics).unwrap();
+
+            // in the open, flying a whole number of steps
+            let step = constants::PROJECTILE_SPEED.value() / SUBPIXELS_PER_PIXEL;
+            let mut projectile = shoot(
+                graphics,
+                320,
+                96,
+                HorizontalFacing::Right,
+                VerticalFacing::Forward,
+                5 * step,
+            );
+            for _ in 0..4 {
+                assert_eq!(projectile.update(&map), Flight::Flying);
+            }
+            assert_eq!(projectile.update(&map), Flight::Expired);
+        });
+    }
+
+    #[test]
+    fn hits_walls() {
+        with_test_graphics(|graphics| {
+            let map = Map::create_test_map(graphics).unwrap();
+
+            // towards the wall in the first column, the hit is reported where the
+            // projectile overlaps it
+            let wall = constants::TILE_SIZE as i32;
+            let step = constants::PROJECTILE_SPEED.value() / SUBPIXELS_PER_PIXEL;
+            let mut projectile = shoot(
+                graphics,
+                wall + HIT_SIZE + 2 * step,
+                96,
+                HorizontalFacing::Left,
+                VerticalFacing::Forward,
+                1000,
+            );
+            assert_eq!(projectile.update(&map), Flight::Flying);
+            assert_eq!(projectile.update(&map), Flight::Flying);
+            assert_eq!(
+                projectile.update(&map),
+                Flight::HitWall(wall + HIT_SIZE - step, 96)
+            );
+
+            // a wall wins over running out of range at the same time
+            let mut projectile = shoot(
+                graphics,
+                wall + HIT_SIZE,
+                96,
+                HorizontalFacing::Left,
+                VerticalFacing::Forward,
+                step,
+            );
+            assert_eq!(
+                projectile.update(&map),
+                Flight::HitWall(wall + HIT_SIZE - step, 96)
+            );
+        });
+    }
+}
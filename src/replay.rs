@@ -0,0 +1,216 @@
+use input::InputEvent;
+use sdl2::controller::{Axis, Button};
+use sdl2::keyboard::Keycode;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::time::Duration;
+
+const HEADER: &str = "cavestory-replay 1";
+
+/// Everything that went into one frame: how much time passed and which input
+/// events arrived.
+pub struct FrameInput {
+    pub elapsed_time: Duration,
+    pub events: Vec<InputEvent>,
+}
+
+/// Writes one line per frame: the elapsed time in nanoseconds followed by the
+/// input events of the frame, `+<keycode>` for key down, `-<keycode>` for key
+/// up, `+button:<name>` and `-button:<name>` for controller buttons,
+/// `axis:<name>=<value>` for stick motion and `removed` for an unplugged
+/// controller.
+pub struct Recorder {
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: &str) -> io::Result<Recorder> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{}", HEADER)?;
+        Ok(Recorder { writer: writer })
+    }
+
+    pub fn record(&mut self, frame: &FrameInput) -> io::Result<()> {
+        write!(self.writer, "{}", frame.elapsed_time.as_nanos())?;
+        for event in &frame.events {
+            match *event {
+                InputEvent::KeyDown(key) => write!(self.writer, " +{}", key as i32)?,
+                InputEvent::KeyUp(key) => write!(self.writer, " -{}", key as i32)?,
+                InputEvent::ButtonDown(button) => {
+                    write!(self.writer, " +button:{}", button.string())?
+                }
+                InputEvent::ButtonUp(button) => {
+                    write!(self.writer, " -button:{}", button.string())?
+                }
+                InputEvent::AxisMotion(axis, value) => {
+                    write!(self.writer, " axis:{}={}", axis.string(), value)?
+                }
+                InputEvent::ControllerRemoved => write!(self.writer, " removed")?,
+            }
+        }
+        writeln!(self.writer)
+    }
+}
+
+pub struct Replay {
+    frames: VecDeque<FrameInput>,
+}
+
+impl Replay {
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let file = File::open(path).map_err(|error| format!("{}: {}", path, error))?;
+        let mut lines = BufReader::new(file).lines();
+
+        match lines.next() {
+            Some(Ok(ref header)) if header == HEADER => (),
+            _ => return Err(format!("{}: not a replay file", path)),
+        }
+
+        let mut frames = VecDeque::new();
+        for (number, line) in lines.enumerate() {
+            let line = line.map_err(|error| format!("{}: {}", path, error))?;
+            let frame = parse_frame(&line)
+                .ok_or_else(|| format!("{}:{}: invalid frame", path, number + 2))?;
+            frames.push_back(frame);
+        }
+
+        Ok(Replay { frames: frames })
+    }
+
+    /// Returns the input of the next frame, or None once the replay is over.
+    pub fn next_frame(&mut self) -> Option<FrameInput> {
+        self.frames.pop_front()
+    }
+}
+
+fn parse_frame(line: &str) -> Option<FrameInput> {
+    let mut fields = line.split_whitespace();
+    let nanos: u64 = fields.next()?.parse().ok()?;
+    let events = fields.map(parse_event).collect::<Option<Vec<_>>>()?;
+
+    Some(FrameInput {
+        elapsed_time: Duration::from_nanos(nanos),
+        events: events,
+    })
+}
+
+fn parse_event(field: &str) -> Option<InputEvent> {
+    if field == "removed" {
+        return Some(InputEvent::ControllerRemoved);
+    }
+    if let Some(axis) = field.strip_prefix("axis:") {
+        let mut parts = axis.splitn(2, '=');
+        let axis = Axis::from_string(parts.next()?)?;
+        return Some(InputEvent::AxisMotion(axis, parts.next()?.parse().ok()?));
+    }
+
+    let (down, control) = match field.as_bytes().first() {
+        Some(&b'+') => (true, &field[1..]),
+        Some(&b'-') => (false, &field[1..]),
+        _ => return None,
+    };
+    if let Some(name) = control.strip_prefix("button:") {
+        let button = Button::from_string(name)?;
+        Some(if down {
+            InputEvent::ButtonDown(button)
+        } else {
+            InputEvent::ButtonUp(button)
+        })
+    } else {
+        let key = Keycode::from_i32(control.parse().ok()?)?;
+        Some(if down {
+            InputEvent::KeyDown(key)
+        } else {
+            InputEvent::KeyUp(key)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    fn temp_path(name: &str) -> String {
+        env::temp_dir()
+            .join(format!("cavestory-{}-{}.replay", process::id(), name))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn write_replay(name: &str, text: &str) -> String {
+        let path = temp_path(name);
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn replays_what_was_recorded() {
+        let frames = vec![
+            FrameInput {
+                elapsed_time: Duration::from_nanos(16_666_667),
+                events: vec![
+                    InputEvent::KeyDown(Keycode::Z),
+                    InputEvent::ButtonDown(Button::A),
+                    InputEvent::AxisMotion(Axis::LeftX, -20000),
+                ],
+            },
+            FrameInput {
+                elapsed_time: Duration::from_millis(17),
+                events: vec![
+                    InputEvent::KeyUp(Keycode::Z),
+                    InputEvent::ButtonUp(Button::A),
+                    InputEvent::AxisMotion(Axis::LeftY, 300),
+                    InputEvent::ControllerRemoved,
+                ],
+            },
+            FrameInput {
+                elapsed_time: Duration::from_millis(16),
+                events: vec![],
+            },
+        ];
+        let path = temp_path("round-trip");
+        {
+            let mut recorder = Recorder::create(&path).unwrap();
+            for frame in &frames {
+                recorder.record(frame).unwrap();
+            }
+        }
+
+        let mut replay = Replay::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        for frame in &frames {
+            let replayed = replay.next_frame().unwrap();
+            assert_eq!(replayed.elapsed_time, frame.elapsed_time);
+            assert_eq!(replayed.events, frame.events);
+        }
+        assert!(replay.next_frame().is_none());
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let path = write_replay("header", "cavestory-replay 2\n16666667 +122\n");
+        let result = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+        match result {
+            Err(error) => assert_eq!(error, format!("{}: not a replay file", path)),
+            Ok(_) => panic!("loaded a replay with the wrong header"),
+        }
+    }
+
+    #[test]
+    fn reports_malformed_frames() {
+        let text = "cavestory-replay 1\n16666667 +122\n16666667 +button:nothing\n";
+        let path = write_replay("malformed", text);
+        let result = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+        match result {
+            Err(error) => assert_eq!(error, format!("{}:3: invalid frame", path)),
+            Ok(_) => panic!("loaded a replay with a malformed frame"),
+        }
+    }
+}
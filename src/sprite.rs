@@ -1,26 +1,44 @@
-use constants;
-use graphics::Graphics;
+use graphics::{AssetError, Depth, Graphics, Transform};
 use sdl2::rect::Rect;
 use sdl2::render::Texture;
 use units::Milliseconds;
 
 use std::rc::Rc;
 
-pub trait Drawable {
-    fn draw(&self, graphics: &mut Graphics, x: i32, y: i32);
+/// Something that can be queued for drawing with textures living for `'a`.
+pub trait Drawable<'a> {
+    fn draw(&self, graphics: &mut Graphics<'a>, depth: Depth, x: i32, y: i32) {
+        self.draw_transformed(graphics, depth, x, y, Transform::none());
+    }
+
+    /// Draws mirrored and/or rotated, so sprites only need to be on the sprite
+    /// sheet facing one way.
+    fn draw_transformed(
+        &self,
+        graphics: &mut Graphics<'a>,
+        depth: Depth,
+        x: i32,
+        y: i32,
+        transform: Transform,
+    );
 }
 
 pub trait Updatable {
     fn update(&mut self, elapsed_time: Milliseconds);
-}
 
-pub trait UpdateAndDrawable: Updatable + Drawable {}
-impl<T> UpdateAndDrawable for T
-where
-    T: Updatable + Drawable,
-{
+    /// Starts over from the beginning.
+    fn reset(&mut self) {}
+
+    /// Whether there is nothing left to play, which only happens for
+    /// animations that play once.
+    fn finished(&self) -> bool {
+        false
+    }
 }
 
+pub trait UpdateAndDrawable<'a>: Updatable + Drawable<'a> {}
+impl<'a, T> UpdateAndDrawable<'a> for T where T: Updatable + Drawable<'a> {}
+
 pub struct Sprite<'a> {
     sprite_sheet: Rc<Texture<'a>>,
     source_rect: Rect,
@@ -34,20 +52,29 @@ impl<'a> Sprite<'a> {
         y: i32,
         width: u32,
         height: u32,
-    ) -> Sprite<'a> {
-        Sprite {
-            sprite_sheet: graphics.load_image(filename),
+    ) -> Result<Sprite<'a>, AssetError> {
+        Ok(Sprite {
+            sprite_sheet: graphics.load_image(filename)?,
             source_rect: Rect::new(x, y, width, height),
-        }
+        })
     }
 }
 
-impl<'a> Drawable for Sprite<'a> {
-    fn draw(&self, graphics: &mut Graphics, x: i32, y: i32) {
+impl<'a> Drawable<'a> for Sprite<'a> {
+    fn draw_transformed(
+        &self,
+        graphics: &mut Graphics<'a>,
+        depth: Depth,
+        x: i32,
+        y: i32,
+        transform: Transform,
+    ) {
         graphics.blit_surface(
-            &self.sprite_sheet,
+            Rc::clone(&self.sprite_sheet),
             self.source_rect,
             Rect::new(x, y, self.source_rect.width(), self.source_rect.height()),
+            transform,
+            depth,
         );
     }
 }
@@ -56,64 +83,284 @@ impl<'a> Updatable for Sprite<'a> {
     fn update(&mut self, _: Milliseconds) {}
 }
 
+/// One frame of an animation: where it is on the sprite sheet and how long it
+/// is shown for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub source: Rect,
+    pub duration: Milliseconds,
+}
+
+impl Frame {
+    pub fn new(source: Rect, duration: Milliseconds) -> Frame {
+        Frame {
+            source: source,
+            duration: duration,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimationMode {
+    /// Starts over at the first frame after the last one.
+    Loop,
+    /// Plays the frames forwards and then backwards again, without repeating
+    /// the first and last frame.
+    PingPong,
+    /// Stops at the last frame.
+    Once,
+}
+
+/// Where an animation goes after showing a frame for its whole duration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Step {
+    frame: usize,
+    reversed: bool,
+    finished: bool,
+}
+
+/// The step after showing frame `current` of `frame_count` frames. `reversed`
+/// is whether a ping-pong animation is playing backwards, play-once animations
+/// are finished once their last frame was shown.
+fn next_frame(mode: AnimationMode, current: usize, reversed: bool, frame_count: usize) -> Step {
+    let last_frame = frame_count - 1;
+    let step = |frame, reversed| Step {
+        frame: frame,
+        reversed: reversed,
+        finished: false,
+    };
+    match mode {
+        AnimationMode::Loop if current == last_frame => step(0, false),
+        AnimationMode::Once if current == last_frame => Step {
+            frame: last_frame,
+            reversed: false,
+            finished: true,
+        },
+        AnimationMode::PingPong if last_frame == 0 => step(0, false),
+        AnimationMode::PingPong => {
+            let reversed = if current == last_frame {
+                true
+            } else if current == 0 {
+                false
+            } else {
+                reversed
+            };
+            if reversed {
+                step(current - 1, true)
+            } else {
+                step(current + 1, false)
+            }
+        }
+        _ => step(current + 1, false),
+    }
+}
+
+#[derive(Clone)]
 pub struct AnimatedSprite<'a> {
     sprite_sheet: Rc<Texture<'a>>,
-    source_rect: Rect,
-    frame_time: Milliseconds,
+    frames: Vec<Frame>,
+    mode: AnimationMode,
     elapsed_time: Milliseconds,
-    num_frames: u32,
-    current_frame: u32,
+    current_frame: usize,
+    // ping-pong animations play backwards half of the time
+    reversed: bool,
+    finished: bool,
 }
 
 impl<'a> AnimatedSprite<'a> {
     pub fn new(
         graphics: &mut Graphics<'a>,
         filename: &str,
-        x: i32,
-        y: i32,
-        width: u32,
-        height: u32,
-        fps: u64,
-        num_frames: u32,
-    ) -> AnimatedSprite<'a> {
-        let frame_time = 1_000 / fps as u32;
-        AnimatedSprite {
-            sprite_sheet: graphics.load_image(filename),
-            source_rect: Rect::new(x, y, width, height),
-            frame_time: Milliseconds::new(frame_time),
+        frames: Vec<Frame>,
+        mode: AnimationMode,
+    ) -> Result<AnimatedSprite<'a>, AssetError> {
+        assert!(!frames.is_empty(), "an animation needs at least one frame");
+        Ok(AnimatedSprite {
+            sprite_sheet: graphics.load_image(filename)?,
+            frames: frames,
+            mode: mode,
             elapsed_time: Milliseconds::new(0),
-            num_frames: num_frames,
             current_frame: 0,
-        }
+            reversed: false,
+            finished: false,
+        })
     }
 }
 
-impl<'a> Drawable for AnimatedSprite<'a> {
-    fn draw(&self, graphics: &mut Graphics, x: i32, y: i32) {
+impl<'a> Drawable<'a> for AnimatedSprite<'a> {
+    fn draw_transformed(
+        &self,
+        graphics: &mut Graphics<'a>,
+        depth: Depth,
+        x: i32,
+        y: i32,
+        transform: Transform,
+    ) {
+        let source = self.frames[self.current_frame].source;
         graphics.blit_surface(
-            &self.sprite_sheet,
-            self.source_rect,
-            Rect::new(x, y, self.source_rect.width(), self.source_rect.height()),
+            Rc::clone(&self.sprite_sheet),
+            source,
+            Rect::new(x, y, source.width(), source.height()),
+            transform,
+            depth,
         );
     }
 }
 
 impl<'a> Updatable for AnimatedSprite<'a> {
     fn update(&mut self, elapsed_time: Milliseconds) {
+        if self.finished() {
+            return;
+        }
         self.elapsed_time += elapsed_time;
 
-        if self.elapsed_time > self.frame_time {
-            self.current_frame += 1;
-            self.elapsed_time -= self.frame_time;
-            if self.current_frame < self.num_frames {
-                let new_val = self.source_rect.x() + constants::TILE_SIZE as i32;
-                self.source_rect.set_x(new_val);
-            } else {
-                let new_val =
-                    self.source_rect.x() - (constants::TILE_SIZE * (self.num_frames - 1)) as i32;
-                self.source_rect.set_x(new_val);
-                self.current_frame = 0;
-            }
+        let frame_time = self.frames[self.current_frame].duration;
+        if self.elapsed_time >= frame_time {
+            self.elapsed_time -= frame_time;
+            let step = next_frame(
+                self.mode,
+                self.current_frame,
+                self.reversed,
+                self.frames.len(),
+            );
+            self.current_frame = step.frame;
+            self.reversed = step.reversed;
+            self.finished = step.finished;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.elapsed_time = Milliseconds::new(0);
+        self.current_frame = 0;
+        self.reversed = false;
+        self.finished = false;
+    }
+
+    fn finished(&self) -> bool {
+        self.finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use constants;
+    use graphics::with_test_graphics;
+
+    /// The frames an animation of `frame_count` frames shows, starting from
+    /// the first one.
+    fn play(mode: AnimationMode, frame_count: usize, steps: usize) -> Vec<usize> {
+        let mut frames = vec![0];
+        let mut reversed = false;
+        for _ in 1..steps {
+            let step = next_frame(mode, *frames.last().unwrap(), reversed, frame_count);
+            reversed = step.reversed;
+            frames.push(step.frame);
         }
+        frames
+    }
+
+    /// An animation of `frame_count` frames showing each of them for 10ms.
+    fn animated_sprite<'a>(
+        graphics: &mut Graphics<'a>,
+        mode: AnimationMode,
+        frame_count: usize,
+    ) -> AnimatedSprite<'a> {
+        let frames = (0..frame_count)
+            .map(|index| {
+                Frame::new(
+                    Rect::new(index as i32 * 32, 0, 32, 32),
+                    Milliseconds::new(10),
+                )
+            })
+            .collect();
+        AnimatedSprite::new(graphics, constants::CARET_SHEET, frames, mode).unwrap()
+    }
+
+    #[test]
+    fn loops() {
+        assert_eq!(play(AnimationMode::Loop, 3, 7), vec![0, 1, 2, 0, 1, 2, 0]);
+        assert_eq!(play(AnimationMode::Loop, 1, 3), vec![0, 0, 0]);
+        // the walk cycle lists the standing frame twice to show 0-1-0-2
+        assert_eq!(
+            play(AnimationMode::Loop, 4, 9),
+            vec![0, 1, 2, 3, 0, 1, 2, 3, 0]
+        );
+    }
+
+    #[test]
+    fn ping_pongs() {
+        assert_eq!(
+            play(AnimationMode::PingPong, 3, 9),
+            vec![0, 1, 2, 1, 0, 1, 2, 1, 0]
+        );
+        assert_eq!(play(AnimationMode::PingPong, 2, 5), vec![0, 1, 0, 1, 0]);
+        assert_eq!(play(AnimationMode::PingPong, 1, 3), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn ping_pong_turns_around_in_the_middle() {
+        // a reversed animation keeps going backwards until the first frame
+        assert_eq!(
+            next_frame(AnimationMode::PingPong, 2, true, 4),
+            Step {
+                frame: 1,
+                reversed: true,
+                finished: false,
+            }
+        );
+    }
+
+    #[test]
+    fn plays_once() {
+        assert_eq!(play(AnimationMode::Once, 3, 5), vec![0, 1, 2, 2, 2]);
+        assert!(!next_frame(AnimationMode::Once, 1, false, 3).finished);
+        assert!(next_frame(AnimationMode::Once, 2, false, 3).finished);
+        assert!(next_frame(AnimationMode::Once, 0, false, 1).finished);
+    }
+
+    #[test]
+    fn finishes_after_showing_the_last_frame() {
+        with_test_graphics(|graphics| {
+            let mut sprite = animated_sprite(graphics, AnimationMode::Once, 2);
+
+            // each frame is shown for exactly its duration
+            sprite.update(Milliseconds::new(9));
+            assert_eq!(sprite.current_frame, 0);
+            sprite.update(Milliseconds::new(1));
+            assert_eq!((sprite.current_frame, sprite.finished()), (1, false));
+            sprite.update(Milliseconds::new(9));
+            assert!(!sprite.finished());
+            sprite.update(Milliseconds::new(1));
+            assert_eq!((sprite.current_frame, sprite.finished()), (1, true));
+            sprite.update(Milliseconds::new(100));
+            assert_eq!(sprite.current_frame, 1);
+        });
+    }
+
+    #[test]
+    fn resets() {
+        with_test_graphics(|graphics| {
+            let mut sprite = animated_sprite(graphics, AnimationMode::Once, 2);
+            for _ in 0..2 {
+                sprite.update(Milliseconds::new(10));
+            }
+            assert!(sprite.finished());
+            sprite.reset();
+            assert_eq!((sprite.current_frame, sprite.finished()), (0, false));
+            assert_eq!(sprite.elapsed_time, Milliseconds::new(0));
+
+            // a ping-pong animation starts forwards again after a reset
+            let mut sprite = animated_sprite(graphics, AnimationMode::PingPong, 3);
+            for _ in 0..3 {
+                sprite.update(Milliseconds::new(10));
+            }
+            assert_eq!((sprite.current_frame, sprite.reversed), (1, true));
+            sprite.reset();
+            sprite.update(Milliseconds::new(10));
+            assert_eq!(sprite.current_frame, 1);
+            sprite.update(Milliseconds::new(10));
+            assert_eq!(sprite.current_frame, 2);
+        });
     }
 }
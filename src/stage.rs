@@ -0,0 +1,329 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+
+const PXM_MAGIC: &[u8] = b"PXM\x10";
+const PXE_MAGIC: &[u8] = b"PXE\x00";
+const PXE_ENTRY_SIZE: usize = 12;
+
+#[derive(Debug)]
+pub enum StageError {
+    Io(String, io::Error),
+    BadMagic(String),
+    Truncated {
+        path: String,
+        expected: usize,
+        found: usize,
+    },
+    TileOutOfRange {
+        path: String,
+        tile: u8,
+        num_attributes: usize,
+    },
+}
+
+impl fmt::Display for StageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StageError::Io(ref path, ref error) => write!(f, "{}: {}", path, error),
+            StageError::BadMagic(ref path) => write!(f, "{}: not a stage file", path),
+            StageError::Truncated {
+                ref path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{}: truncated, expected {} bytes but found {}",
+                path, expected, found
+            ),
+            StageError::TileOutOfRange {
+                ref path,
+                tile,
+                num_attributes,
+            } => write!(
+                f,
+                "{}: tile {} has no attribute, tileset only has {}",
+                path, tile, num_attributes
+            ),
+        }
+    }
+}
+
+impl Error for StageError {}
+
+/// Tile indices of a stage, as stored in a .pxm file. Tiles are stored row by row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TileLayout {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<u8>,
+}
+
+impl TileLayout {
+    pub fn tile(&self, row: usize, col: usize) -> u8 {
+        self.tiles[row * self.width + col]
+    }
+}
+
+/// A single entity placement from a .pxe file. Positions are in tiles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntitySpawn {
+    pub x: u16,
+    pub y: u16,
+    pub flag_id: u16,
+    pub event: u16,
+    pub entity_type: u16,
+    pub flags: u16,
+}
+
+pub struct Stage {
+    pub layout: TileLayout,
+    pub attributes: Vec<u8>,
+    pub entities: Vec<EntitySpawn>,
+}
+
+impl Stage {
+    /// Loads `<stage>.pxm` and `<stage>.pxe` together with the attributes of the
+    /// tileset the stage is drawn with, `<tileset>.pxa`, from `directory`.
+    pub fn load(directory: &str, stage: &str, tileset: &str) -> Result<Stage, StageError> {
+        let pxm_path = format!("{}/{}.pxm", directory, stage);
+        let pxa_path = format!("{}/{}.pxa", directory, tileset);
+        let pxe_path = format!("{}/{}.pxe", directory, stage);
+
+        let layout = parse_pxm(&pxm_path, &read_file(&pxm_path)?)?;
+        let attributes = parse_pxa(&read_file(&pxa_path)?);
+        let entities = parse_pxe(&pxe_path, &read_file(&pxe_path)?)?;
+
+        if let Some(&tile) = layout
+            .tiles
+            .iter()
+            .find(|&&tile| tile as usize >= attributes.len())
+        {
+            return Err(StageError::TileOutOfRange {
+                path: pxm_path,
+                tile: tile,
+                num_attributes: attributes.len(),
+            });
+        }
+
+        Ok(Stage {
+            layout: layout,
+            attributes: attributes,
+            entities: entities,
+        })
+    }
+
+    pub fn attribute(&self, row: usize, col: usize) -> u8 {
+        self.attributes[self.layout.tile(row, col) as usize]
+    }
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, StageError> {
+    let mut data = Vec::new();
+    File::open(Path::new(path))
+        .and_then(|mut file| file.read_to_end(&mut data))
+        .map_err(|error| StageError::Io(path.to_string(), error))?;
+    Ok(data)
+}
+
+fn check_length(path: &str, data: &[u8], expected: usize) -> Result<(), StageError> {
+    if data.len() < expected {
+        Err(StageError::Truncated {
+            path: path.to_string(),
+            expected: expected,
+            found: data.len(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    data[offset] as u16 | (data[offset + 1] as u16) << 8
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    read_u16(data, offset) as u32 | (read_u16(data, offset + 2) as u32) << 16
+}
+
+/// Parses a tile layout: the magic `PXM\x10`, width and height as little endian
+/// u16, followed by one tile index per tile.
+pub fn parse_pxm(path: &str, data: &[u8]) -> Result<TileLayout, StageError> {
+    check_length(path, data, PXM_MAGIC.len())?;
+    if &data[..PXM_MAGIC.len()] != PXM_MAGIC {
+        return Err(StageError::BadMagic(path.to_string()));
+    }
+    check_length(path, data, 8)?;
+    let width = read_u16(data, 4) as usize;
+    let height = read_u16(data, 6) as usize;
+    check_length(path, data, 8 + width * height)?;
+
+    Ok(TileLayout {
+        width: width,
+        height: height,
+        tiles: data[8..8 + width * height].to_vec(),
+    })
+}
+
+/// Tile attributes have no header, they are one byte per tile in the tileset.
+pub fn parse_pxa(data: &[u8]) -> Vec<u8> {
+    data.to_vec()
+}
+
+/// Parses an entity list: the magic `PXE\0`, the number of entities as little
+/// endian u32, followed by 12 bytes per entity.
+pub fn parse_pxe(path: &str, data: &[u8]) -> Result<Vec<EntitySpawn>, StageError> {
+    check_length(path, data, PXE_MAGIC.len())?;
+    if &data[..PXE_MAGIC.len()] != PXE_MAGIC {
+        return Err(StageError::BadMagic(path.to_string()));
+    }
+    check_length(path, data, 8)?;
+    let count = read_u32(data, 4) as usize;
+    check_length(path, data, 8 + count * PXE_ENTRY_SIZE)?;
+
+    Ok((0..count)
+        .map(|i| {
+            let offset = 8 + i * PXE_ENTRY_SIZE;
+            EntitySpawn {
+                x: read_u16(data, offset),
+                y: read_u16(data, offset + 2),
+                flag_id: read_u16(data, offset + 4),
+                event: read_u16(data, offset + 6),
+                entity_type: read_u16(data, offset + 8),
+                flags: read_u16(data, offset + 10),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PXM: &[u8] = include_bytes!("../tests/fixtures/Test.pxm");
+    const PXA: &[u8] = include_bytes!("../tests/fixtures/Test.pxa");
+    const PXE: &[u8] = include_bytes!("../tests/fixtures/Test.pxe");
+
+    #[test]
+    fn parses_pxm() {
+        let layout = parse_pxm("Test.pxm", PXM).unwrap();
+        assert_eq!(layout.width, 4);
+        assert_eq!(layout.height, 3);
+        assert_eq!(layout.tiles, vec![1, 1, 1, 1, 1, 0, 0, 2, 1, 1, 1, 1]);
+        assert_eq!(layout.tile(1, 3), 2);
+    }
+
+    #[test]
+    fn rejects_truncated_pxm() {
+        match parse_pxm("Test.pxm", &PXM[..PXM.len() - 1]) {
+            Err(StageError::Truncated {
+                expected, found, ..
+            }) => {
+                assert_eq!(expected, 20);
+                assert_eq!(found, 19);
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        match parse_pxm("Test.pxm", &PXM[..6]) {
+            Err(StageError::Truncated { expected, .. }) => assert_eq!(expected, 8),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn rejects_pxm_with_bad_magic() {
+        match parse_pxm("Test.pxe", PXE) {
+            Err(StageError::BadMagic(path)) => assert_eq!(path, "Test.pxe"),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn parses_pxa() {
+        let attributes = parse_pxa(PXA);
+        assert_eq!(attributes.len(), 16);
+        assert_eq!(&attributes[..3], &[0x00, 0x41, 0x43]);
+    }
+
+    #[test]
+    fn parses_pxe() {
+        let entities = parse_pxe("Test.pxe", PXE).unwrap();
+        assert_eq!(
+            entities,
+            vec![
+                EntitySpawn {
+                    x: 1,
+                    y: 1,
+                    flag_id: 0,
+                    event: 0,
+                    entity_type: 1,
+                    flags: 0,
+                },
+                EntitySpawn {
+                    x: 2,
+                    y: 1,
+                    flag_id: 200,
+                    event: 100,
+                    entity_type: 46,
+                    flags: 0x8000,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_pxe() {
+        match parse_pxe("Test.pxe", &PXE[..PXE.len() - PXE_ENTRY_SIZE]) {
+            Err(StageError::Truncated {
+                expected, found, ..
+            }) => {
+                assert_eq!(expected, 32);
+                assert_eq!(found, 20);
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn rejects_pxe_with_bad_magic() {
+        match parse_pxe("Test.pxm", PXM) {
+            Err(StageError::BadMagic(_)) => (),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn loads_stage() {
+        let stage = Stage::load("tests/fixtures", "Test", "Test").unwrap();
+        assert_eq!(stage.attribute(0, 0), 0x41);
+        assert_eq!(stage.attribute(1, 1), 0x00);
+        assert_eq!(stage.attribute(1, 3), 0x43);
+        assert_eq!(stage.entities.len(), 2);
+    }
+
+    #[test]
+    fn rejects_tiles_without_attributes() {
+        match Stage::load("tests/fixtures", "Test", "Short") {
+            Err(StageError::TileOutOfRange {
+                tile,
+                num_attributes,
+                ..
+            }) => {
+                assert_eq!(tile, 2);
+                assert_eq!(num_attributes, 2);
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn reports_missing_files() {
+        match Stage::load("tests/fixtures", "Missing", "Test") {
+            Err(StageError::Io(path, _)) => assert_eq!(path, "tests/fixtures/Missing.pxm"),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::time::Duration;
+use units::Milliseconds;
+
+/// Splits up real elapsed time into fixed size simulation steps, so that the
+/// simulation does not depend on the frame rate.
+pub struct FixedTimestep {
+    step: Milliseconds,
+    max_frame_time: Duration,
+    accumulator: Duration,
+}
+
+impl FixedTimestep {
+    pub fn new(step: Milliseconds, max_frame_time: Milliseconds) -> FixedTimestep {
+        FixedTimestep {
+            step: step,
+            max_frame_time: max_frame_time.to_duration(),
+            accumulator: Duration::new(0, 0),
+        }
+    }
+
+    pub fn step(&self) -> Milliseconds {
+        self.step
+    }
+
+    /// Adds the real time that passed. Very long frames (e.g. when the window
+    /// was being dragged) are cut short rather than simulated in full.
+    pub fn accumulate(&mut self, elapsed_time: Duration) {
+        self.accumulator += elapsed_time.min(self.max_frame_time);
+    }
+
+    /// Consumes one step of accumulated time, returns false if there isn't
+    /// enough left.
+    pub fn tick(&mut self) -> bool {
+        let step = self.step.to_duration();
+        if self.accumulator >= step {
+            self.accumulator -= step;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// How far we are into the next step, from 0 to 1. Used to interpolate
+    /// between the last two simulated states when drawing.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.to_duration().as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs frames that took `frame_times` milliseconds, returns how many
+    /// steps each of them simulated and the alpha they were drawn with.
+    fn run(frame_times: &[u64]) -> Vec<(u32, f32)> {
+        let mut timestep = FixedTimestep::new(Milliseconds::new(10), Milliseconds::new(50));
+        frame_times
+            .iter()
+            .map(|&frame_time| {
+                timestep.accumulate(Duration::from_millis(frame_time));
+                let mut steps = 0;
+                while timestep.tick() {
+                    steps += 1;
+                }
+                (steps, timestep.alpha())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn splits_frames_into_steps() {
+        let frames = run(&[16, 16, 16, 4]);
+        let steps: Vec<u32> = frames.iter().map(|&(steps, _)| steps).collect();
+        assert_eq!(steps, vec![1, 2, 1, 1]);
+        let alphas: Vec<f32> = frames.iter().map(|&(_, alpha)| alpha).collect();
+        for (alpha, expected) in alphas.iter().zip(&[0.6, 0.2, 0.8, 0.2]) {
+            assert!((alpha - expected).abs() < 1e-4, "{} != {}", alpha, expected);
+        }
+    }
+
+    #[test]
+    fn same_frame_times_simulate_the_same() {
+        let frame_times = [7, 33, 16, 16, 1, 120, 9, 17];
+        assert_eq!(run(&frame_times), run(&frame_times));
+    }
+
+    #[test]
+    fn simulates_the_same_time_at_any_frame_rate() {
+        // a second at 50 and at 25 frames per second
+        let total = |frames: Vec<(u32, f32)>| frames.iter().map(|&(steps, _)| steps).sum::<u32>();
+        assert_eq!(total(run(&[20; 50])), 100);
+        assert_eq!(total(run(&[40; 25])), 100);
+    }
+
+    #[test]
+    fn cuts_long_frames_short() {
+        assert_eq!(run(&[1000]), vec![(5, 0.0)]);
+    }
+}
@@ -1,4 +1,4 @@
-use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
 use std::time::Duration;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -9,10 +9,6 @@ impl Milliseconds {
         Milliseconds(milliseconds)
     }
 
-    pub fn from_duration(duration: Duration) -> Self {
-        Milliseconds((duration.as_secs() * 1000) as u32 + duration.subsec_nanos() / 1_000_000)
-    }
-
     pub fn to_duration(self) -> Duration {
         Duration::from_millis(self.0 as u64)
     }
@@ -53,3 +49,118 @@ impl SubAssign for Milliseconds {
         self.0 -= other.0;
     }
 }
+
+/// Positions are kept in 1/512ths of a pixel, like the original game does, so
+/// that slow movement still adds up instead of being rounded away every step.
+pub const SUBPIXELS_PER_PIXEL: i32 = 512;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position(i32);
+
+impl Position {
+    pub const fn new(subpixels: i32) -> Self {
+        Position(subpixels)
+    }
+
+    pub fn from_pixels(pixels: i32) -> Self {
+        Position(pixels * SUBPIXELS_PER_PIXEL)
+    }
+
+    /// Rounds down to the pixel the position is in.
+    pub fn to_pixels(self) -> i32 {
+        self.0.div_euclid(SUBPIXELS_PER_PIXEL)
+    }
+
+    pub fn value(self) -> i32 {
+        self.0
+    }
+
+    /// The pixel `alpha` of the way from `previous` to `self`. The simulation
+    /// runs in fixed steps while frames are drawn as often as they can be, so
+    /// whatever moves keeps where it was before the last step and is drawn
+    /// between the two, `alpha` being how far into the next step the frame is
+    /// (see `FixedTimestep::alpha`).
+    pub fn interpolate(self, previous: Position, alpha: f32) -> i32 {
+        let delta = self.0 - previous.0;
+        Position::new(previous.0 + (delta as f32 * alpha).round() as i32).to_pixels()
+    }
+}
+
+/// Subpixels per update step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Velocity(i32);
+
+impl Velocity {
+    pub const fn new(subpixels: i32) -> Self {
+        Velocity(subpixels)
+    }
+
+    /// Multiplies by `numerator / denominator`, rounding towards zero.
+    pub fn scale(self, numerator: i32, denominator: i32) -> Self {
+        Velocity(self.0 * numerator / denominator)
+    }
+
+    pub fn value(self) -> i32 {
+        self.0
+    }
+}
+
+/// Subpixels per update step, per update step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Acceleration(i32);
+
+impl Acceleration {
+    pub const fn new(subpixels: i32) -> Self {
+        Acceleration(subpixels)
+    }
+}
+
+impl Add<Velocity> for Position {
+    type Output = Position;
+
+    #[inline]
+    fn add(self, velocity: Velocity) -> Position {
+        Position(self.0 + velocity.0)
+    }
+}
+
+impl AddAssign<Velocity> for Position {
+    #[inline]
+    fn add_assign(&mut self, velocity: Velocity) {
+        self.0 += velocity.0;
+    }
+}
+
+impl Add<Acceleration> for Velocity {
+    type Output = Velocity;
+
+    #[inline]
+    fn add(self, acceleration: Acceleration) -> Velocity {
+        Velocity(self.0 + acceleration.0)
+    }
+}
+
+impl AddAssign<Acceleration> for Velocity {
+    #[inline]
+    fn add_assign(&mut self, acceleration: Acceleration) {
+        self.0 += acceleration.0;
+    }
+}
+
+impl Neg for Velocity {
+    type Output = Velocity;
+
+    #[inline]
+    fn neg(self) -> Velocity {
+        Velocity(-self.0)
+    }
+}
+
+impl Neg for Acceleration {
+    type Output = Acceleration;
+
+    #[inline]
+    fn neg(self) -> Acceleration {
+        Acceleration(-self.0)
+    }
+}
@@ -0,0 +1,357 @@
+use constants;
+use graphics::{AssetError, Depth, Graphics, Layer, Transform};
+use player::{HorizontalFacing, VerticalFacing};
+use projectile::{Impact, Projectile};
+use sdl2::rect::Rect;
+use sprite::{AnimatedSprite, AnimationMode, Drawable, Frame, Sprite};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use units::Milliseconds;
+
+const IMPACT_FRAMES: i32 = 4;
+const IMPACT_FRAME_TIME: u32 = 40; // milliseconds
+const MAX_LEVEL: u32 = 3;
+
+// experience needed to fill each level, and for each level how far its
+// projectiles fly in pixels and how many of them can be on screen at once
+const POLAR_STAR_EXPERIENCE: [u32; 3] = [10, 20, 10];
+const POLAR_STAR_LEVELS: [(i32, usize); 3] = [(160, 2), (224, 2), (288, 3)];
+
+/// A weapon going up or down a level, with the level it is at now.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LevelChange {
+    Up(u32),
+    Down(u32),
+}
+
+/// Experience of a weapon and the level it puts the weapon at, from 1 to
+/// `MAX_LEVEL`. Filling a level moves up to the next one with the rest of the
+/// points, losing more points than the level has moves down into a full
+/// previous level.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Experience {
+    // points needed to fill each level
+    thresholds: [u32; 3],
+    level: u32,
+    points: u32,
+}
+
+impl Experience {
+    pub fn new(thresholds: [u32; 3]) -> Experience {
+        Experience {
+            thresholds: thresholds,
+            level: 1,
+            points: 0,
+        }
+    }
+
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    /// Points towards filling the current level.
+    pub fn points(&self) -> u32 {
+        self.points
+    }
+
+    /// Points the current level holds.
+    pub fn threshold(&self) -> u32 {
+        self.thresholds[self.level as usize - 1]
+    }
+
+    /// Whether the last level is full, more points are wasted.
+    pub fn is_max(&self) -> bool {
+        self.level == MAX_LEVEL && self.points == self.threshold()
+    }
+
+    pub fn gain(&mut self, points: u32) -> Option<LevelChange> {
+        let mut change = None;
+        self.points += points;
+        while self.level < MAX_LEVEL && self.points >= self.threshold() {
+            self.points -= self.threshold();
+            self.level += 1;
+            change = Some(LevelChange::Up(self.level));
+        }
+        self.points = self.points.min(self.threshold());
+        change
+    }
+
+    pub fn lose(&mut self, points: u32) -> Option<LevelChange> {
+        let mut change = None;
+        let mut remaining = points;
+        while remaining > self.points {
+            if self.level == 1 {
+                self.points = 0;
+                return change;
+            }
+            remaining -= self.points;
+            self.level -= 1;
+            self.points = self.threshold();
+            change = Some(LevelChange::Down(self.level));
+        }
+        self.points -= remaining;
+        change
+    }
+}
+
+/// What a weapon shoots at one of its levels.
+struct WeaponLevel<'a> {
+    projectile: Rc<Sprite<'a>>,
+    // how far projectiles fly in pixels
+    range: i32,
+    max_projectiles: usize,
+}
+
+/// Where the gun is drawn relative to the player's sprite when they face left.
+/// Facing right mirrors it around the middle of the sprite.
+fn gun_offset(vertical: VerticalFacing) -> (i32, i32) {
+    match vertical {
+        VerticalFacing::Up => (-4, -12),
+        VerticalFacing::Forward => (-12, 4),
+        VerticalFacing::Down => (-4, 14),
+    }
+}
+
+/// Where projectiles come out of the gun, relative to the player's sprite
+/// facing left like `gun_offset`.
+fn muzzle_offset(vertical: VerticalFacing) -> (i32, i32) {
+    match vertical {
+        VerticalFacing::Up => (12, -10),
+        VerticalFacing::Forward => (-10, 20),
+        VerticalFacing::Down => (12, 43),
+    }
+}
+
+/// A gun the player holds: how it looks in their hand, what it shoots at
+/// each level and its experience.
+pub struct Weapon<'a> {
+    // pointing left, in the direction the player looks
+    guns: BTreeMap<VerticalFacing, Sprite<'a>>,
+    levels: Vec<WeaponLevel<'a>>,
+    impact: AnimatedSprite<'a>,
+    experience: Experience,
+}
+
+impl<'a> Weapon<'a> {
+    pub fn polar_star(graphics: &mut Graphics<'a>) -> Result<Weapon<'a>, AssetError> {
+        let tile_size = constants::TILE_SIZE;
+        let mut guns = BTreeMap::new();
+        for (index, &vertical) in [
+            VerticalFacing::Forward,
+            VerticalFacing::Up,
+            VerticalFacing::Down,
+        ]
+        .iter()
+        .enumerate()
+        {
+            let x = index as i32 * tile_size as i32;
+            guns.insert(
+                vertical,
+                Sprite::new(graphics, constants::ARMS_SHEET, x, 0, tile_size, tile_size)?,
+            );
+        }
+
+        // the projectiles of each level are next to each other on the sheet
+        let mut levels = Vec::new();
+        for (index, &(range, max_projectiles)) in POLAR_STAR_LEVELS.iter().enumerate() {
+            levels.push(WeaponLevel {
+                projectile: Rc::new(Sprite::new(
+                    graphics,
+                    constants::BULLET_SHEET,
+                    index as i32 * tile_size as i32,
+                    0,
+                    tile_size,
+                    tile_size,
+                )?),
+                range: range,
+                max_projectiles: max_projectiles,
+            });
+        }
+
+        let frames = (0..IMPACT_FRAMES)
+            .map(|index| {
+                Frame::new(
+                    Rect::new(index * tile_size as i32, 0, tile_size, tile_size),
+                    Milliseconds::new(IMPACT_FRAME_TIME),
+                )
+            })
+            .collect();
+        Ok(Weapon {
+            guns: guns,
+            levels: levels,
+            impact: AnimatedSprite::new(
+                graphics,
+                constants::CARET_SHEET,
+                frames,
+                AnimationMode::Once,
+            )?,
+            experience: Experience::new(POLAR_STAR_EXPERIENCE),
+        })
+    }
+
+    /// Draws the gun in the hand of a player whose sprite is at `x`, `y` on
+    /// screen.
+    pub fn draw(
+        &self,
+        graphics: &mut Graphics<'a>,
+        x: i32,
+        y: i32,
+        horizontal: HorizontalFacing,
+        vertical: VerticalFacing,
+    ) {
+        let (offset_x, offset_y) = gun_offset(vertical);
+        let (offset_x, transform) = match horizontal {
+            HorizontalFacing::Left => (offset_x, Transform::none()),
+            HorizontalFacing::Right => (
+                -offset_x,
+                Transform {
+                    flip_horizontal: true,
+                    ..Transform::none()
+                },
+            ),
+        };
+        self.guns[&vertical].draw_transformed(
+            graphics,
+            Depth::new(Layer::Entities, 1),
+            x + offset_x,
+            y + offset_y,
+            transform,
+        );
+    }
+
+    /// Shoots from the hand of a player whose sprite is at `x`, `y` in world
+    /// pixels, unless `active` projectiles of this weapon are already flying
+    /// and that's as many as its level allows.
+    pub fn fire(
+        &self,
+        x: i32,
+        y: i32,
+        horizontal: HorizontalFacing,
+        vertical: VerticalFacing,
+        active: usize,
+    ) -> Option<Projectile<'a>> {
+        let level = &self.levels[self.experience.level() as usize - 1];
+        if active >= level.max_projectiles {
+            return None;
+        }
+        let (offset_x, offset_y) = muzzle_offset(vertical);
+        let offset_x = match horizontal {
+            HorizontalFacing::Left => offset_x,
+            HorizontalFacing::Right => constants::TILE_SIZE as i32 - offset_x,
+        };
+        Some(Projectile::new(
+            Rc::clone(&level.projectile),
+            x + offset_x,
+            y + offset_y,
+            horizontal,
+            vertical,
+            level.range,
+        ))
+    }
+
+    pub fn experience(&self) -> &Experience {
+        &self.experience
+    }
+
+    pub fn experience_mut(&mut self) -> &mut Experience {
+        &mut self.experience
+    }
+
+    /// The effect for a projectile hitting something at `x`, `y` in world
+    /// pixels.
+    pub fn impact(&self, x: i32, y: i32) -> Impact<'a> {
+        Impact::new(self.impact.clone(), x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics::with_test_graphics;
+
+    #[test]
+    fn gains_levels() {
+        let mut experience = Experience::new([10, 20, 10]);
+        assert_eq!(experience.gain(9), None);
+        assert_eq!((experience.level(), experience.points()), (1, 9));
+        assert_eq!(experience.gain(1), Some(LevelChange::Up(2)));
+        assert_eq!((experience.level(), experience.points()), (2, 0));
+        // the rest of the points count towards the next level
+        assert_eq!(experience.gain(25), Some(LevelChange::Up(3)));
+        assert_eq!((experience.level(), experience.points()), (3, 5));
+    }
+
+    #[test]
+    fn gains_several_levels_at_once() {
+        let mut experience = Experience::new([10, 20, 10]);
+        assert_eq!(experience.gain(35), Some(LevelChange::Up(3)));
+        assert_eq!((experience.level(), experience.points()), (3, 5));
+    }
+
+    #[test]
+    fn stops_at_max() {
+        let mut experience = Experience::new([10, 20, 10]);
+        experience.gain(39);
+        assert!(!experience.is_max());
+        assert_eq!(experience.gain(100), None);
+        assert_eq!((experience.level(), experience.points()), (3, 10));
+        assert!(experience.is_max());
+    }
+
+    #[test]
+    fn loses_levels() {
+        let mut experience = Experience::new([10, 20, 10]);
+        experience.gain(13);
+        assert_eq!(experience.lose(3), None);
+        assert_eq!((experience.level(), experience.points()), (2, 0));
+        // going down a level starts from a full level
+        assert_eq!(experience.lose(2), Some(LevelChange::Down(1)));
+        assert_eq!((experience.level(), experience.points()), (1, 8));
+    }
+
+    #[test]
+    fn loses_several_levels_at_once() {
+        let mut experience = Experience::new([10, 20, 10]);
+        experience.gain(34);
+        assert_eq!(experience.lose(30), Some(LevelChange::Down(1)));
+        assert_eq!((experience.level(), experience.points()), (1, 4));
+    }
+
+    #[test]
+    fn stops_at_zero() {
+        let mut experience = Experience::new([10, 20, 10]);
+        experience.gain(12);
+        assert_eq!(experience.lose(50), Some(LevelChange::Down(1)));
+        assert_eq!((experience.level(), experience.points()), (1, 0));
+        assert_eq!(experience.lose(5), None);
+        assert_eq!((experience.level(), experience.points()), (1, 0));
+    }
+
+    #[test]
+    fn fires_up_to_max_projectiles() {
+        with_test_graphics(|graphics| {
+            let mut weapon = Weapon::polar_star(graphics).unwrap();
+            let fire = |weapon: &Weapon, active| {
+                weapon
+                    .fire(
+                        0,
+                        0,
+                        HorizontalFacing::Left,
+                        VerticalFacing::Forward,
+                        active,
+                    )
+                    .is_some()
+            };
+
+            assert!(fire(&weapon, 0));
+            assert!(fire(&weapon, 1));
+            assert!(!fire(&weapon, 2));
+
+            // one more at the last level
+            weapon.experience_mut().gain(30);
+            assert_eq!(weapon.experience().level(), 3);
+            assert!(fire(&weapon, 2));
+            assert!(!fire(&weapon, 3));
+        });
+    }
+}